@@ -0,0 +1,299 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use super::channel_query::*;
+use super::client::Pusher;
+use super::json_structures::*;
+use super::transport::Transport;
+use super::util::SocketId;
+
+/// The future returned by the asynchronous methods of `PusherApi`.
+pub type ApiFuture<'a, R> = Pin<Box<dyn Future<Output = Result<R, String>> + Send + 'a>>;
+
+/// The operations of `Pusher`, as a trait, so that application code can be
+/// written against it and given a fake in its unit tests. `Pusher` implements
+/// it for any `Transport`, and behind the `testing` feature
+/// `pusher::testing::FakePusher` implements it in memory.
+///
+/// The methods behave as `Pusher`'s methods of the same name. Requests are
+/// validated and built when the method is called, so the returned futures
+/// only borrow `self`.
+///
+/// `Pusher::publisher` is left out, as its background task needs a `Pusher`
+/// of its own, and so is `Pusher::channel_users_stream`, which only adapts
+/// `channel_users`.
+///
+/// **Example:**
+///
+/// ```
+/// use pusher::PusherApi;
+///
+/// async fn notify<P: PusherApi>(pusher: &P, user_id: &str) -> Result<(), String> {
+///     let channel = format!("private-user-{}", user_id);
+///     pusher.trigger(&channel, "notification", "You have mail").await?;
+///     Ok(())
+/// }
+/// ```
+pub trait PusherApi: Send + Sync {
+    fn trigger<S: serde::Serialize>(
+        &self,
+        channel: &str,
+        event: &str,
+        payload: S,
+    ) -> ApiFuture<'_, TriggeredEvents>;
+
+    fn trigger_exclusive<S, I>(
+        &self,
+        channel: &str,
+        event: &str,
+        payload: S,
+        socket_id: I,
+    ) -> ApiFuture<'_, TriggeredEvents>
+    where
+        S: serde::Serialize,
+        I: TryInto<SocketId, Error = &'static str>;
+
+    fn trigger_multi<S: serde::Serialize>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+    ) -> ApiFuture<'_, TriggeredEvents>;
+
+    fn trigger_multi_exclusive<S, I>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+        socket_id: I,
+    ) -> ApiFuture<'_, TriggeredEvents>
+    where
+        S: serde::Serialize,
+        I: TryInto<SocketId, Error = &'static str>;
+
+    fn trigger_many_channels<S: serde::Serialize>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+        concurrency: usize,
+    ) -> ApiFuture<'_, TriggeredEvents>;
+
+    fn trigger_batch(&self, events: Vec<BatchEvent>) -> ApiFuture<'_, TriggeredBatch>;
+
+    fn channels(&self) -> ApiFuture<'_, ChannelList>;
+
+    fn channels_with_options(&self, params: QueryParameters) -> ApiFuture<'_, ChannelList>;
+
+    fn channels_with_query(&self, query: ChannelsQuery) -> ApiFuture<'_, ChannelList>;
+
+    fn channel(&self, channel_name: &str) -> ApiFuture<'_, Channel>;
+
+    fn channel_with_options(
+        &self,
+        channel_name: &str,
+        params: QueryParameters,
+    ) -> ApiFuture<'_, Channel>;
+
+    fn channel_with_query(&self, channel_name: &str, query: ChannelQuery)
+        -> ApiFuture<'_, Channel>;
+
+    fn channel_users(&self, channel_name: &str) -> ApiFuture<'_, ChannelUserList>;
+
+    fn authenticate_private_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+    ) -> Result<String, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>;
+
+    fn authenticate_presence_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+        member: &Member,
+    ) -> Result<String, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>;
+
+    fn authorize_private_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+    ) -> Result<ChannelAuthResponse, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>;
+
+    fn authorize_presence_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+        member: &Member,
+    ) -> Result<ChannelAuthResponse, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>;
+
+    fn webhook(&self, key: &str, signature: &str, body: &str) -> Result<Webhook, &str>;
+}
+
+impl<T: Transport> PusherApi for Pusher<T> {
+    fn trigger<S: serde::Serialize>(
+        &self,
+        channel: &str,
+        event: &str,
+        payload: S,
+    ) -> ApiFuture<'_, TriggeredEvents> {
+        let channels = vec![channel.to_string()];
+        self._trigger(channels, event, payload, None)
+    }
+
+    fn trigger_exclusive<S, I>(
+        &self,
+        channel: &str,
+        event: &str,
+        payload: S,
+        socket_id: I,
+    ) -> ApiFuture<'_, TriggeredEvents>
+    where
+        S: serde::Serialize,
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        let channels = vec![channel.to_string()];
+        match socket_id.try_into() {
+            Ok(socket_id) => self._trigger(channels, event, payload, Some(socket_id)),
+            Err(err) => Box::pin(async move { Err(err.to_string()) }),
+        }
+    }
+
+    fn trigger_multi<S: serde::Serialize>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+    ) -> ApiFuture<'_, TriggeredEvents> {
+        let channels = channels.iter().map(|c| (*c).to_string()).collect();
+        self._trigger(channels, event, payload, None)
+    }
+
+    fn trigger_multi_exclusive<S, I>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+        socket_id: I,
+    ) -> ApiFuture<'_, TriggeredEvents>
+    where
+        S: serde::Serialize,
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        let channels = channels.iter().map(|c| (*c).to_string()).collect();
+        match socket_id.try_into() {
+            Ok(socket_id) => self._trigger(channels, event, payload, Some(socket_id)),
+            Err(err) => Box::pin(async move { Err(err.to_string()) }),
+        }
+    }
+
+    fn trigger_many_channels<S: serde::Serialize>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+        concurrency: usize,
+    ) -> ApiFuture<'_, TriggeredEvents> {
+        self._trigger_many_channels(channels, event, payload, concurrency)
+    }
+
+    fn trigger_batch(&self, events: Vec<BatchEvent>) -> ApiFuture<'_, TriggeredBatch> {
+        Box::pin(Pusher::trigger_batch(self, events))
+    }
+
+    fn channels(&self) -> ApiFuture<'_, ChannelList> {
+        Box::pin(Pusher::channels(self))
+    }
+
+    fn channels_with_options(&self, params: QueryParameters) -> ApiFuture<'_, ChannelList> {
+        Box::pin(Pusher::channels_with_options(self, params))
+    }
+
+    fn channels_with_query(&self, query: ChannelsQuery) -> ApiFuture<'_, ChannelList> {
+        Box::pin(Pusher::channels_with_query(self, query))
+    }
+
+    fn channel(&self, channel_name: &str) -> ApiFuture<'_, Channel> {
+        self._channel(channel_name, None)
+    }
+
+    fn channel_with_options(
+        &self,
+        channel_name: &str,
+        params: QueryParameters,
+    ) -> ApiFuture<'_, Channel> {
+        self._channel(channel_name, Some(params))
+    }
+
+    fn channel_with_query(
+        &self,
+        channel_name: &str,
+        query: ChannelQuery,
+    ) -> ApiFuture<'_, Channel> {
+        match query.to_query_parameters(channel_name) {
+            Ok(params) => self._channel(channel_name, Some(params)),
+            Err(err) => Box::pin(async move { Err(err) }),
+        }
+    }
+
+    fn channel_users(&self, channel_name: &str) -> ApiFuture<'_, ChannelUserList> {
+        self._channel_users(channel_name)
+    }
+
+    fn authenticate_private_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+    ) -> Result<String, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        Pusher::authenticate_private_channel(self, channel_name, socket_id)
+    }
+
+    fn authenticate_presence_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+        member: &Member,
+    ) -> Result<String, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        Pusher::authenticate_presence_channel(self, channel_name, socket_id, member)
+    }
+
+    fn authorize_private_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+    ) -> Result<ChannelAuthResponse, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        Pusher::authorize_private_channel(self, channel_name, socket_id)
+    }
+
+    fn authorize_presence_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+        member: &Member,
+    ) -> Result<ChannelAuthResponse, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        Pusher::authorize_presence_channel(self, channel_name, socket_id, member)
+    }
+
+    fn webhook(&self, key: &str, signature: &str, body: &str) -> Result<Webhook, &str> {
+        Pusher::webhook(self, key, signature, body)
+    }
+}
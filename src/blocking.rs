@@ -0,0 +1,591 @@
+//! A synchronous client, for command line tools and frameworks that do not run
+//! an async runtime. It is enabled by the `blocking` feature.
+//!
+//! `blocking::Pusher` mirrors the methods of `pusher::Pusher`, but each call
+//! blocks the current thread until the request completes. Requests are sent
+//! with a [`ureq`](https://docs.rs/ureq) agent by default, or with any other
+//! `BlockingTransport`.
+//!
+//! ```no_run
+//! use pusher::blocking::PusherBuilder;
+//!
+//! let pusher = PusherBuilder::new("APP_ID", "KEY", "SECRET").finalize();
+//! match pusher.trigger("test_channel", "my_event", "hello world!") {
+//!   Ok(events) => println!("Successfully published: {:?}", events),
+//!   Err(err) => println!("Failed to publish: {}", err),
+//! }
+//! ```
+
+use std::fmt;
+use std::thread;
+use ureq::Agent;
+
+use super::channel_query::*;
+use super::clock::Clock;
+use super::connector::DEFAULT_SECURE;
+use super::json_structures::*;
+use super::observer::{Observation, RequestObserver, Target};
+use super::rate_limit::{RateLimit, Throttle};
+use super::transport::{BlockingTransport, TransportRequest, TransportResponse};
+use super::util::{validate_channels, EventName, SocketId};
+
+/// A synchronous version of `pusher::Pusher`. It is cheap to clone.
+pub struct Pusher<T = Agent> {
+    inner: super::Pusher<T>,
+}
+
+/// An ephemeral object upon which to pass configuration options to when
+/// initializing a `blocking::Pusher` instance.
+pub struct PusherBuilder<T = Agent> {
+    inner: super::PusherBuilder<T>,
+}
+
+impl PusherBuilder {
+    /// Initializes a client with a default `ureq` agent. See
+    /// `pusher::PusherBuilder::new` for more detail.
+    pub fn new(app_id: &str, key: &str, secret: &str) -> PusherBuilder {
+        let mut builder = PusherBuilder::new_with_client(default_agent(), app_id, key, secret);
+        builder.inner.secure = DEFAULT_SECURE;
+        builder
+    }
+
+    /// Initializes a client from a Pusher URL. See `pusher::PusherBuilder::from_url`
+    /// for more detail.
+    pub fn from_url(url: &str) -> PusherBuilder {
+        PusherBuilder::from_url_with_client(default_agent(), url)
+    }
+
+    /// Initializes a client from an environment variable Pusher URL. See
+    /// `pusher::PusherBuilder::from_env` for more detail.
+    pub fn from_env(key: &str) -> PusherBuilder {
+        PusherBuilder::from_env_with_client(default_agent(), key)
+    }
+}
+
+impl<T> PusherBuilder<T> {
+    /// Initializes the client with a specified `BlockingTransport`.
+    pub fn new_with_client(
+        http_client: T,
+        app_id: &str,
+        key: &str,
+        secret: &str,
+    ) -> PusherBuilder<T> {
+        PusherBuilder {
+            inner: super::PusherBuilder::new_with_client(http_client, app_id, key, secret),
+        }
+    }
+
+    /// Initializes the client with a specified `BlockingTransport`.
+    /// See pusher::PusherBuilder::from_url for more detail.
+    pub fn from_url_with_client(http_client: T, url: &str) -> PusherBuilder<T> {
+        PusherBuilder {
+            inner: super::PusherBuilder::from_url_with_client(http_client, url),
+        }
+    }
+
+    /// Initializes the client with a specified `BlockingTransport`.
+    /// See pusher::PusherBuilder::from_env for more detail.
+    pub fn from_env_with_client(http_client: T, key: &str) -> PusherBuilder<T> {
+        PusherBuilder {
+            inner: super::PusherBuilder::from_env_with_client(http_client, key),
+        }
+    }
+
+    /// This method changes the host to which API requests will be made.
+    /// This defaults to `api.pusherapp.com`.
+    pub fn host(mut self, host: &str) -> PusherBuilder<T> {
+        self.inner = self.inner.host(host);
+        self
+    }
+
+    /// This method sets the port to which API requests will be made.
+    pub fn port(mut self, port: u16) -> PusherBuilder<T> {
+        self.inner = self.inner.port(port);
+        self
+    }
+
+    /// This method sets a path prefix for the HTTP API. See
+    /// `pusher::PusherBuilder::base_path`.
+    pub fn base_path(mut self, base_path: &str) -> PusherBuilder<T> {
+        self.inner = self.inner.base_path(base_path);
+        self
+    }
+
+    /// This method sets the clock used to timestamp requests. See
+    /// `pusher::PusherBuilder::clock`.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> PusherBuilder<T> {
+        self.inner = self.inner.clock(clock);
+        self
+    }
+
+    /// This method adds a `RequestObserver`, called before and after every
+    /// request. See `pusher::PusherBuilder::observer`.
+    pub fn observer<O: RequestObserver + 'static>(mut self, observer: O) -> PusherBuilder<T> {
+        self.inner = self.inner.observer(observer);
+        self
+    }
+
+    /// This method limits the rate at which messages are triggered. Delayed
+    /// triggers block the calling thread. See `pusher::PusherBuilder::rate_limit`.
+    pub fn rate_limit(mut self, limit: RateLimit) -> PusherBuilder<T> {
+        self.inner = self.inner.rate_limit(limit);
+        self
+    }
+
+    /// This method sets the most channels a single trigger may name. See
+    /// `pusher::PusherBuilder::max_channels_per_trigger`.
+    pub fn max_channels_per_trigger(mut self, max: usize) -> PusherBuilder<T> {
+        self.inner = self.inner.max_channels_per_trigger(max);
+        self
+    }
+
+    /// This method sets the encryption master key, from which the shared
+    /// secrets of encrypted channels are derived. See
+    /// `pusher::PusherBuilder::encryption_master_key_base64`.
+    pub fn encryption_master_key_base64(mut self, key: &str) -> Result<PusherBuilder<T>, String> {
+        self.inner = self.inner.encryption_master_key_base64(key)?;
+        Ok(self)
+    }
+
+    /// This method makes requests go over HTTPS.
+    pub fn secure(mut self) -> PusherBuilder<T> {
+        self.inner = self.inner.secure();
+        self
+    }
+
+    /// This method makes requests go over plain HTTP.
+    pub fn insecure(mut self) -> PusherBuilder<T> {
+        self.inner = self.inner.insecure();
+        self
+    }
+
+    /// If you wish to configure a `ureq::Agent`, or use any other
+    /// `BlockingTransport`, pass it in to this method.
+    pub fn client(mut self, http_client: T) -> PusherBuilder<T> {
+        self.inner = self.inner.client(http_client);
+        self
+    }
+
+    /// This method actually creates the `blocking::Pusher` instance from your
+    /// chained configuration.
+    pub fn finalize(self) -> Pusher<T> {
+        Pusher {
+            inner: self.inner.finalize(),
+        }
+    }
+}
+
+impl<T> Clone for Pusher<T> {
+    fn clone(&self) -> Pusher<T> {
+        Pusher {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Pusher<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<T> Pusher<T> {
+    /// Your app_id from <http://app.pusher.com>
+    pub fn app_id(&self) -> &str {
+        self.inner.app_id()
+    }
+
+    /// Your key from <http://app.pusher.com>
+    pub fn key(&self) -> &str {
+        self.inner.key()
+    }
+
+    /// The host requests are sent to.
+    pub fn host(&self) -> &str {
+        self.inner.host()
+    }
+
+    /// The port requests are sent to, if not the scheme's default.
+    pub fn port(&self) -> Option<u16> {
+        self.inner.port()
+    }
+
+    /// The path prefix of the HTTP API.
+    pub fn base_path(&self) -> &str {
+        self.inner.base_path()
+    }
+
+    /// If true, requests are made over HTTPS.
+    pub fn secure(&self) -> bool {
+        self.inner.secure()
+    }
+
+    /// The transport requests are sent with; by default a `ureq::Agent`.
+    pub fn http_client(&self) -> &T {
+        self.inner.http_client()
+    }
+
+    /// See `pusher::Pusher::authenticate_private_channel`.
+    pub fn authenticate_private_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+    ) -> Result<String, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        self.inner
+            .authenticate_private_channel(channel_name, socket_id)
+    }
+
+    /// See `pusher::Pusher::authenticate_presence_channel`.
+    pub fn authenticate_presence_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+        member: &Member,
+    ) -> Result<String, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        self.inner
+            .authenticate_presence_channel(channel_name, socket_id, member)
+    }
+
+    /// See `pusher::Pusher::authorize_private_channel`.
+    pub fn authorize_private_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+    ) -> Result<ChannelAuthResponse, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        self.inner
+            .authorize_private_channel(channel_name, socket_id)
+    }
+
+    /// See `pusher::Pusher::authorize_presence_channel`.
+    pub fn authorize_presence_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+        member: &Member,
+    ) -> Result<ChannelAuthResponse, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        self.inner
+            .authorize_presence_channel(channel_name, socket_id, member)
+    }
+
+    /// See `pusher::Pusher::webhook`.
+    pub fn webhook(&self, key: &str, signature: &str, body: &str) -> Result<Webhook, &str> {
+        self.inner.webhook(key, signature, body)
+    }
+}
+
+impl<T: BlockingTransport> Pusher<T> {
+    /// Triggers an event on a single channel. See `pusher::Pusher::trigger`.
+    pub fn trigger<S: serde::Serialize>(
+        &self,
+        channel: &str,
+        event: &str,
+        payload: S,
+    ) -> Result<TriggeredEvents, String> {
+        let channels = vec![channel.to_string()];
+        self._trigger(channels, event, payload, None)
+    }
+
+    /// Triggers an event, excluding the connection with the given `socket_id`.
+    /// See `pusher::Pusher::trigger_exclusive`.
+    pub fn trigger_exclusive<S, I>(
+        &self,
+        channel: &str,
+        event: &str,
+        payload: S,
+        socket_id: I,
+    ) -> Result<TriggeredEvents, String>
+    where
+        S: serde::Serialize,
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        let socket_id = socket_id.try_into()?;
+        let channels = vec![channel.to_string()];
+        self._trigger(channels, event, payload, Some(socket_id))
+    }
+
+    /// Triggers an event on multiple channels. See `pusher::Pusher::trigger_multi`.
+    pub fn trigger_multi<S: serde::Serialize>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+    ) -> Result<TriggeredEvents, String> {
+        let channel_strings = channels.iter().map(|c| (*c).to_string()).collect();
+        self._trigger(channel_strings, event, payload, None)
+    }
+
+    /// Triggers an event on multiple channels, excluding the connection with
+    /// the given `socket_id`. See `pusher::Pusher::trigger_multi_exclusive`.
+    pub fn trigger_multi_exclusive<S, I>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+        socket_id: I,
+    ) -> Result<TriggeredEvents, String>
+    where
+        S: serde::Serialize,
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        let socket_id = socket_id.try_into()?;
+        let channel_strings = channels.iter().map(|c| (*c).to_string()).collect();
+        self._trigger(channel_strings, event, payload, Some(socket_id))
+    }
+
+    /// Triggers an event on any number of channels, with one request after
+    /// another for as many as the channel limit requires. See
+    /// `pusher::Pusher::trigger_many_channels`.
+    pub fn trigger_many_channels<S: serde::Serialize>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+    ) -> Result<TriggeredEvents, String> {
+        let max_channels = self.inner.config().max_channels;
+        if channels.len() <= max_channels {
+            return self.trigger_multi(channels, event, payload);
+        }
+        let channel_strings: Vec<String> = channels.iter().map(|c| (*c).to_string()).collect();
+        validate_channels(&channel_strings, channel_strings.len())?;
+        EventName::new(event)?.check_channels(&channel_strings)?;
+
+        let mut triggered = TriggeredEvents::default();
+        for chunk in channel_strings.chunks(max_channels) {
+            triggered.merge(self._trigger(chunk.to_vec(), event, &payload, None)?);
+        }
+        Ok(triggered)
+    }
+
+    fn _trigger<S: serde::Serialize>(
+        &self,
+        channels: Vec<String>,
+        event: &str,
+        payload: S,
+        socket_id: Option<SocketId>,
+    ) -> Result<TriggeredEvents, String> {
+        #[cfg(feature = "tracing")]
+        let _span = crate::trace::trigger_span(event, channels.len()).entered();
+        let request =
+            self.inner
+                .config()
+                .trigger_request(channels.clone(), event, payload, socket_id)?;
+        let target = Target::event(event, &channels);
+        self.send(target, request)
+    }
+
+    /// Triggers up to 10 events in a single request. See
+    /// `pusher::Pusher::trigger_batch`.
+    pub fn trigger_batch(&self, events: Vec<BatchEvent>) -> Result<TriggeredBatch, String> {
+        #[cfg(feature = "tracing")]
+        let _span = crate::trace::batch_span(events.len()).entered();
+        let request = self.inner.config().batch_trigger_request(&events)?;
+        let channels: Vec<String> = events.into_iter().map(|event| event.channel).collect();
+        self.send(Target::batch(&channels), request)
+    }
+
+    /// Lists the channels in the application. See `pusher::Pusher::channels`.
+    pub fn channels(&self) -> Result<ChannelList, String> {
+        self.send(
+            Target::default(),
+            self.inner.config().channels_request(None),
+        )
+    }
+
+    /// See `pusher::Pusher::channels_with_options`.
+    pub fn channels_with_options(&self, params: QueryParameters) -> Result<ChannelList, String> {
+        let request = self.inner.config().channels_request(Some(params));
+        self.send(Target::default(), request)
+    }
+
+    /// See `pusher::Pusher::channels_with_query`.
+    pub fn channels_with_query(&self, query: ChannelsQuery) -> Result<ChannelList, String> {
+        let params = query.to_query_parameters()?;
+        self.channels_with_options(params)
+    }
+
+    /// Gets the state of a single channel. See `pusher::Pusher::channel`.
+    pub fn channel(&self, channel_name: &str) -> Result<Channel, String> {
+        let request = self.inner.config().channel_request(channel_name, None);
+        self.send(Target::channels(&[channel_name.to_string()]), request)
+    }
+
+    /// See `pusher::Pusher::channel_with_options`.
+    pub fn channel_with_options(
+        &self,
+        channel_name: &str,
+        params: QueryParameters,
+    ) -> Result<Channel, String> {
+        let request = self
+            .inner
+            .config()
+            .channel_request(channel_name, Some(params));
+        self.send(Target::channels(&[channel_name.to_string()]), request)
+    }
+
+    /// See `pusher::Pusher::channel_with_query`.
+    pub fn channel_with_query(
+        &self,
+        channel_name: &str,
+        query: ChannelQuery,
+    ) -> Result<Channel, String> {
+        let params = query.to_query_parameters(channel_name)?;
+        self.channel_with_options(channel_name, params)
+    }
+
+    /// Retrieves the users subscribed to a presence-channel. See
+    /// `pusher::Pusher::channel_users`.
+    pub fn channel_users(&self, channel_name: &str) -> Result<ChannelUserList, String> {
+        let request = self.inner.config().channel_users_request(channel_name);
+        self.send(Target::channels(&[channel_name.to_string()]), request)
+    }
+
+    fn send<R: serde::de::DeserializeOwned>(
+        &self,
+        target: Target,
+        request: TransportRequest,
+    ) -> Result<R, String> {
+        let config = self.inner.config();
+        let (mut throttle, wait) = Throttle::start(config, &target)?;
+        let mut request = request;
+        if !wait.is_zero() {
+            thread::sleep(wait);
+            config.resign(&mut request);
+        }
+
+        let observation = Observation::start(&config.observers, &request, target);
+        let response = loop {
+            let retry = throttle.may_retry().then(|| request.clone());
+            let response = self.send_attempt(request, throttle.attempt());
+            match (retry, throttle.retry_delay(&response)) {
+                (Some(retry), Some(delay)) => {
+                    thread::sleep(delay);
+                    request = retry;
+                    config.resign(&mut request);
+                }
+                _ => break response,
+            }
+        };
+        observation.finish(response).map_err(String::from)
+    }
+
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    fn send_attempt(
+        &self,
+        request: TransportRequest,
+        attempt: u32,
+    ) -> Result<TransportResponse, String> {
+        let send = |request| self.inner.http_client().send(request);
+        #[cfg(feature = "tracing")]
+        let send = |request: TransportRequest| {
+            let span = crate::trace::request_span(&request, attempt);
+            crate::trace::record_blocking(span, || send(request))
+        };
+        send(request)
+    }
+}
+
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+fn default_agent() -> Agent {
+    match native_tls::TlsConnector::new() {
+        Ok(connector) => ureq::AgentBuilder::new()
+            .tls_connector(std::sync::Arc::new(connector))
+            .build(),
+        Err(_) => Agent::new(),
+    }
+}
+
+#[cfg(not(all(feature = "native-tls", not(feature = "rustls"))))]
+fn default_agent() -> Agent {
+    Agent::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::TransportResponse;
+    use std::sync::Mutex;
+
+    struct CannedTransport {
+        requests: Mutex<Vec<TransportRequest>>,
+        body: &'static str,
+    }
+
+    impl BlockingTransport for CannedTransport {
+        fn send(&self, request: TransportRequest) -> Result<TransportResponse, String> {
+            self.requests.lock().unwrap().push(request);
+            Ok(TransportResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: self.body.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    fn canned(body: &'static str) -> Pusher<CannedTransport> {
+        let transport = CannedTransport {
+            requests: Mutex::new(Vec::new()),
+            body,
+        };
+        PusherBuilder::new_with_client(transport, "1", "key", "secret")
+            .host("127.0.0.1")
+            .finalize()
+    }
+
+    #[test]
+    fn test_blocking_trigger() {
+        let pusher = canned("{\"event_ids\":{\"test_channel\":\"eudhq1809scss2\"}}");
+        let events = pusher.trigger("test_channel", "my_event", "hello").unwrap();
+        assert_eq!(events.event_ids.unwrap()["test_channel"], "eudhq1809scss2");
+        let requests = pusher.http_client().requests.lock().unwrap();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].url.path(), "/apps/1/events")
+    }
+
+    #[test]
+    fn test_blocking_trigger_many_channels() {
+        let pusher = canned("{\"event_ids\":{\"a\":\"eudhq1809scss2\"}}");
+        let channels: Vec<String> = (0..150).map(|i| i.to_string()).collect();
+        let channels: Vec<&str> = channels.iter().map(String::as_str).collect();
+        let events = pusher
+            .trigger_many_channels(&channels, "my_event", "hello")
+            .unwrap();
+        assert_eq!(events.event_ids.unwrap()["a"], "eudhq1809scss2");
+        let requests = pusher.http_client().requests.lock().unwrap();
+        assert_eq!(requests.len(), 2)
+    }
+
+    #[test]
+    fn test_blocking_channel_users() {
+        let pusher = canned("{\"users\":[{\"id\":\"red\"},{\"id\":\"blue\"}]}");
+        let users = pusher.channel_users("presence-yolo").unwrap().users;
+        assert_eq!(users[1].id, "blue");
+        let requests = pusher.http_client().requests.lock().unwrap();
+        assert_eq!(
+            requests[0].url.path(),
+            "/apps/1/channels/presence-yolo/users"
+        )
+    }
+
+    #[test]
+    fn test_blocking_validation() {
+        let pusher = canned("{}");
+        let res = pusher.channel_with_query(
+            "private-foo",
+            ChannelQuery::new().info(ChannelInfo::UserCount),
+        );
+        assert!(res.is_err());
+        assert!(pusher.http_client().requests.lock().unwrap().is_empty())
+    }
+}
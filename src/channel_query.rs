@@ -0,0 +1,240 @@
+use super::json_structures::QueryParameters;
+use super::util::ChannelType;
+
+/// An attribute that can be requested when querying the state of channels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChannelInfo {
+    /// The number of distinct users subscribed to a presence-channel.
+    UserCount,
+    /// The number of connections subscribed to the channel. Subscription
+    /// counting must be enabled for your app.
+    SubscriptionCount,
+    /// The cached event for a cache-channel, if any. Only a `ChannelQuery`
+    /// can request it, as the HTTP API does not return cached events when
+    /// listing channels.
+    Cache,
+}
+
+impl ChannelInfo {
+    /// The name of the attribute, as used in the `info` query parameter.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ChannelInfo::UserCount => "user_count",
+            ChannelInfo::SubscriptionCount => "subscription_count",
+            ChannelInfo::Cache => "cache",
+        }
+    }
+}
+
+/// Typed options for querying a list of channels with `channels_with_query`.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::{ChannelInfo, ChannelsQuery};
+/// let query = ChannelsQuery::new()
+///     .filter_by_prefix("presence-")
+///     .info(ChannelInfo::UserCount);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChannelsQuery {
+    filter_by_prefix: Option<String>,
+    info: Vec<ChannelInfo>,
+}
+
+impl ChannelsQuery {
+    /// Creates an empty query, which lists every occupied channel.
+    pub fn new() -> ChannelsQuery {
+        ChannelsQuery::default()
+    }
+
+    /// Only returns channels whose name starts with `prefix`.
+    pub fn filter_by_prefix(mut self, prefix: &str) -> ChannelsQuery {
+        self.filter_by_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Requests an additional attribute for each returned channel.
+    pub fn info(mut self, info: ChannelInfo) -> ChannelsQuery {
+        if !self.info.contains(&info) {
+            self.info.push(info);
+        }
+        self
+    }
+
+    /// Validates the combination of options and converts them into the
+    /// query parameters sent to the HTTP API. `ChannelInfo::Cache` is always
+    /// rejected, since cached events are only returned for a single channel.
+    pub fn to_query_parameters(&self) -> Result<QueryParameters, String> {
+        let prefix = self.filter_by_prefix.as_deref().unwrap_or("");
+        for info in &self.info {
+            match *info {
+                ChannelInfo::UserCount if !prefix.starts_with("presence-") => {
+                    return Err(
+                        "user_count can only be requested when filtering by a presence- prefix"
+                            .to_string(),
+                    );
+                }
+                ChannelInfo::Cache => {
+                    return Err("cache can only be requested for a single channel".to_string());
+                }
+                _ => {}
+            }
+        }
+
+        let mut params = Vec::new();
+        if let Some(ref prefix) = self.filter_by_prefix {
+            params.push(("filter_by_prefix".to_string(), prefix.clone()));
+        }
+        if !self.info.is_empty() {
+            params.push(("info".to_string(), join_info(&self.info)));
+        }
+        Ok(params)
+    }
+}
+
+/// Typed options for querying a single channel with `channel_with_query`.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::{ChannelInfo, ChannelQuery};
+/// let query = ChannelQuery::new()
+///     .info(ChannelInfo::UserCount)
+///     .info(ChannelInfo::SubscriptionCount);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChannelQuery {
+    info: Vec<ChannelInfo>,
+}
+
+impl ChannelQuery {
+    /// Creates an empty query, which only returns whether the channel is occupied.
+    pub fn new() -> ChannelQuery {
+        ChannelQuery::default()
+    }
+
+    /// Requests an additional attribute for the channel.
+    pub fn info(mut self, info: ChannelInfo) -> ChannelQuery {
+        if !self.info.contains(&info) {
+            self.info.push(info);
+        }
+        self
+    }
+
+    /// Validates the options against the channel being queried and converts
+    /// them into the query parameters sent to the HTTP API.
+    pub fn to_query_parameters(&self, channel_name: &str) -> Result<QueryParameters, String> {
+        let channel_type = ChannelType::from_name(channel_name);
+        for info in &self.info {
+            match *info {
+                ChannelInfo::UserCount if !channel_type.is_presence() => {
+                    return Err(
+                        "user_count can only be requested for presence- channels".to_string()
+                    );
+                }
+                ChannelInfo::Cache if !channel_type.is_cache() => {
+                    return Err("cache can only be requested for cache channels".to_string());
+                }
+                _ => {}
+            }
+        }
+
+        if self.info.is_empty() {
+            Ok(Vec::new())
+        } else {
+            Ok(vec![("info".to_string(), join_info(&self.info))])
+        }
+    }
+}
+
+fn join_info(info: &[ChannelInfo]) -> String {
+    info.iter()
+        .map(|i| i.as_str())
+        .collect::<Vec<&str>>()
+        .join(",")
+}
+
+#[test]
+fn test_channels_query_parameters() {
+    let params = ChannelsQuery::new()
+        .filter_by_prefix("presence-")
+        .info(ChannelInfo::UserCount)
+        .to_query_parameters()
+        .unwrap();
+    assert_eq!(
+        params,
+        vec![
+            ("filter_by_prefix".to_string(), "presence-".to_string()),
+            ("info".to_string(), "user_count".to_string()),
+        ]
+    )
+}
+
+#[test]
+fn test_channels_query_user_count_requires_presence_prefix() {
+    let res = ChannelsQuery::new()
+        .filter_by_prefix("private-")
+        .info(ChannelInfo::UserCount)
+        .to_query_parameters();
+    assert_eq!(
+        res.unwrap_err(),
+        "user_count can only be requested when filtering by a presence- prefix"
+    )
+}
+
+#[test]
+fn test_channels_query_rejects_cache() {
+    let res = ChannelsQuery::new()
+        .filter_by_prefix("cache-")
+        .info(ChannelInfo::Cache)
+        .to_query_parameters();
+    assert_eq!(
+        res.unwrap_err(),
+        "cache can only be requested for a single channel"
+    )
+}
+
+#[test]
+fn test_channel_query_parameters() {
+    let params = ChannelQuery::new()
+        .info(ChannelInfo::UserCount)
+        .info(ChannelInfo::SubscriptionCount)
+        .info(ChannelInfo::UserCount)
+        .to_query_parameters("presence-foo")
+        .unwrap();
+    assert_eq!(
+        params,
+        vec![(
+            "info".to_string(),
+            "user_count,subscription_count".to_string()
+        )]
+    )
+}
+
+#[test]
+fn test_channel_query_cache_requires_cache_channel() {
+    let params = ChannelQuery::new()
+        .info(ChannelInfo::Cache)
+        .to_query_parameters("private-cache-foo")
+        .unwrap();
+    assert_eq!(params, vec![("info".to_string(), "cache".to_string())]);
+    let res = ChannelQuery::new()
+        .info(ChannelInfo::Cache)
+        .to_query_parameters("private-foo");
+    assert_eq!(
+        res.unwrap_err(),
+        "cache can only be requested for cache channels"
+    )
+}
+
+#[test]
+fn test_channel_query_user_count_requires_presence_channel() {
+    let res = ChannelQuery::new()
+        .info(ChannelInfo::UserCount)
+        .to_query_parameters("private-foo");
+    assert_eq!(
+        res.unwrap_err(),
+        "user_count can only be requested for presence- channels"
+    )
+}
@@ -0,0 +1,49 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A source of the current time, used to timestamp signed requests and to
+/// check the timestamps of received ones.
+///
+/// It defaults to `SystemClock`. Tests can set a fixed time instead, so that
+/// signatures are deterministic; any `Fn() -> SystemTime` is a `Clock`.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::PusherBuilder;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let pusher = PusherBuilder::new("id", "key", "secret")
+///   .clock(|| UNIX_EPOCH + Duration::from_secs(1353088179))
+///   .finalize();
+/// ```
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The system's clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> SystemTime + Send + Sync,
+{
+    fn now(&self) -> SystemTime {
+        self()
+    }
+}
+
+/// The number of seconds since the Unix epoch, as used in `auth_timestamp`.
+pub fn unix_timestamp(clock: &dyn Clock) -> u64 {
+    clock
+        .now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use url::Url;
+
+use super::clock::{unix_timestamp, Clock};
+use super::interceptor::Interceptor;
+use super::json_structures::*;
+use super::observer::RequestObserver;
+use super::proxy::ProxyConnector;
+use super::rate_limit::RateLimiter;
+use super::request_url::*;
+use super::signature::*;
+use super::transport::TransportRequest;
+use super::util::*;
+
+/// The credentials and location of an app, from which signed requests to the
+/// HTTP API are built. This holds everything that does not depend on how the
+/// requests are sent.
+#[derive(Clone)]
+pub struct Config {
+    pub app_id: String,
+    pub key: String,
+    pub secret: String,
+    pub host: String,
+    pub port: Option<u16>,
+    pub base_path: String,
+    pub secure: bool,
+    pub clock: Arc<dyn Clock>,
+    pub observers: Vec<Arc<dyn RequestObserver>>,
+    pub interceptors: Vec<Arc<dyn Interceptor>>,
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Bounds the number of requests in flight at once.
+    pub in_flight: Option<Arc<Semaphore>>,
+    /// The most channels a single trigger request may name.
+    pub max_channels: usize,
+    /// The key from which the shared secrets of encrypted channels are derived.
+    pub encryption_master_key: Option<[u8; 32]>,
+    /// The proxy of the default client, whose credentials are added to plain
+    /// HTTP requests.
+    pub proxy: Option<ProxyConnector<()>>,
+}
+
+impl Config {
+    fn scheme(&self) -> &str {
+        if self.secure {
+            "https"
+        } else {
+            "http"
+        }
+    }
+
+    fn signed_request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<String>,
+        params: Option<QueryParameters>,
+    ) -> TransportRequest {
+        let authority = match self.port {
+            Some(port) => format!("{}:{}", self.host, port),
+            None => self.host.clone(),
+        };
+        let request_url_string = format!(
+            "{}://{}{}/apps/{}{}",
+            self.scheme(),
+            authority,
+            self.base_path,
+            self.app_id,
+            path
+        );
+        let mut request_url = Url::parse(&request_url_string).unwrap();
+        let query = build_query(
+            method,
+            request_url.path(),
+            &self.key,
+            &self.secret,
+            unix_timestamp(&*self.clock).to_string(),
+            body.as_deref(),
+            params,
+        );
+        request_url.set_query(Some(&query));
+        TransportRequest::new(method, request_url, body)
+    }
+
+    /// Signs a request again with the current time, keeping its other query
+    /// parameters, so that one sent after a wait is not rejected for having
+    /// an expired `auth_timestamp`.
+    pub fn resign(&self, request: &mut TransportRequest) {
+        let params: QueryParameters = request
+            .url
+            .query_pairs()
+            .filter(|(k, _)| !k.starts_with("auth_") && k != "body_md5")
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        let query = build_query(
+            &request.method,
+            request.url.path(),
+            &self.key,
+            &self.secret,
+            unix_timestamp(&*self.clock).to_string(),
+            request.body.as_deref(),
+            Some(params),
+        );
+        request.url.set_query(Some(&query));
+    }
+
+    pub fn trigger_request<S: serde::Serialize>(
+        &self,
+        channels: Vec<String>,
+        event: &str,
+        payload: S,
+        socket_id: Option<SocketId>,
+    ) -> Result<TransportRequest, String> {
+        let event_name = EventName::new(event)?;
+        validate_channels(&channels, self.max_channels)?;
+        event_name.check_channels(&channels)?;
+
+        let json_payload = serde_json::to_string(&payload).unwrap();
+
+        let raw_body = TriggerEventData {
+            name: event.to_string(),
+            channels,
+            data: json_payload,
+            socket_id,
+        };
+
+        let body = serde_json::to_string(&raw_body).unwrap();
+
+        if body.len() > 10240 {
+            return Err("Data must be smaller than 10kb".to_string());
+        }
+
+        Ok(self.signed_request("POST", "/events", Some(body), None))
+    }
+
+    pub fn batch_trigger_request(&self, events: &[BatchEvent]) -> Result<TransportRequest, String> {
+        if events.len() > 10 {
+            return Err("Cannot trigger more than 10 events in a batch".to_string());
+        }
+        for event in events {
+            validate_batch_event(event)?;
+        }
+
+        let body = serde_json::to_string(&BatchEventData { batch: events }).unwrap();
+        Ok(self.signed_request("POST", "/batch_events", Some(body), None))
+    }
+
+    pub fn channels_request(&self, params: Option<QueryParameters>) -> TransportRequest {
+        self.signed_request("GET", "/channels", None, params)
+    }
+
+    pub fn channel_request(
+        &self,
+        channel_name: &str,
+        params: Option<QueryParameters>,
+    ) -> TransportRequest {
+        let path = format!("/channels/{}", channel_name);
+        self.signed_request("GET", &path, None, params)
+    }
+
+    pub fn channel_users_request(&self, channel_name: &str) -> TransportRequest {
+        let path = format!("/channels/{}/users", channel_name);
+        self.signed_request("GET", &path, None, None)
+    }
+
+    pub fn authenticate_channel(
+        &self,
+        channel_name: &str,
+        socket_id: &SocketId,
+        member: Option<&Member>,
+    ) -> ChannelAuthResponse {
+        let mut to_sign = format!("{}:{}", socket_id, channel_name);
+
+        let channel_data =
+            member.map(|presence_member| serde_json::to_string(presence_member).unwrap());
+        if let Some(ref json_member) = channel_data {
+            to_sign = format!("{}:{}", to_sign, json_member);
+        }
+
+        let shared_secret = match self.encryption_master_key {
+            Some(ref master_key) if ChannelType::from_name(channel_name).is_encrypted() => {
+                Some(create_shared_secret(channel_name, master_key))
+            }
+            _ => None,
+        };
+
+        ChannelAuthResponse {
+            auth: create_channel_auth(&self.key, &self.secret, &to_sign),
+            channel_data,
+            shared_secret,
+        }
+    }
+
+    pub fn webhook(&self, key: &str, signature: &str, body: &str) -> Result<Webhook, &'static str> {
+        if self.key == key && check_signature(signature, &self.secret, body) {
+            return serde_json::from_str(body).map_err(|_| "Invalid webhook");
+        }
+        Err("Invalid webhook")
+    }
+}
@@ -0,0 +1,117 @@
+use hyper::Client;
+use std::time::Duration;
+
+use super::proxy::{Proxy, ProxyConnector};
+
+/// The connector used by the clients `PusherBuilder::new`, `from_url` and
+/// `from_env` construct. With the `rustls` or `native-tls` feature enabled it
+/// can connect over HTTPS; otherwise it is a plain `HttpConnector`. Either way
+/// it connects through a `ProxyConnector`, which may have a proxy configured.
+pub type DefaultConnector = imp::Connector;
+
+/// Whether the default client can make HTTPS requests, in which case they are
+/// made over HTTPS unless configured otherwise.
+pub const DEFAULT_SECURE: bool = cfg!(any(feature = "rustls", feature = "native-tls"));
+
+pub fn default_client() -> Client<DefaultConnector> {
+    ClientOptions::default().build()
+}
+
+/// The settings of the default client, which `PusherBuilder` accumulates and
+/// rebuilds the client from as they change.
+#[derive(Clone, Debug, Default)]
+pub struct ClientOptions {
+    pub proxy: ProxySetting,
+    pub pool_idle_timeout: Option<Duration>,
+    pub pool_max_idle_per_host: Option<usize>,
+    pub http2_prior_knowledge: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum ProxySetting {
+    #[default]
+    Direct,
+    Proxy(Proxy),
+    FromEnv,
+}
+
+impl ClientOptions {
+    pub fn build(&self) -> Client<DefaultConnector> {
+        let proxy_connector = self.proxy_connector(imp::http_connector());
+        let mut builder = Client::builder();
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder.pool_max_idle_per_host(max_idle);
+        }
+        builder.http2_only(self.http2_prior_knowledge);
+        builder.build(imp::connector(proxy_connector))
+    }
+
+    pub fn proxy_connector<C>(&self, inner: C) -> ProxyConnector<C> {
+        let proxy_connector = ProxyConnector::new(inner);
+        match self.proxy {
+            ProxySetting::Direct => proxy_connector,
+            ProxySetting::Proxy(ref proxy) => proxy_connector.proxy(proxy.clone()),
+            ProxySetting::FromEnv => proxy_connector.proxy_from_env(),
+        }
+    }
+}
+
+#[cfg(feature = "rustls")]
+mod imp {
+    use super::ProxyConnector;
+    use hyper::client::HttpConnector;
+
+    pub type Connector = hyper_rustls::HttpsConnector<ProxyConnector<HttpConnector>>;
+
+    pub fn http_connector() -> HttpConnector {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        http
+    }
+
+    pub fn connector(proxy_connector: ProxyConnector<HttpConnector>) -> Connector {
+        hyper_rustls::HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_or_http()
+            .enable_http1()
+            .enable_http2()
+            .wrap_connector(proxy_connector)
+    }
+}
+
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+mod imp {
+    use super::ProxyConnector;
+    use hyper::client::HttpConnector;
+
+    pub type Connector = hyper_tls::HttpsConnector<ProxyConnector<HttpConnector>>;
+
+    pub fn http_connector() -> HttpConnector {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        http
+    }
+
+    pub fn connector(proxy_connector: ProxyConnector<HttpConnector>) -> Connector {
+        hyper_tls::HttpsConnector::new_with_connector(proxy_connector)
+    }
+}
+
+#[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+mod imp {
+    use super::ProxyConnector;
+    use hyper::client::HttpConnector;
+
+    pub type Connector = ProxyConnector<HttpConnector>;
+
+    pub fn http_connector() -> HttpConnector {
+        HttpConnector::new()
+    }
+
+    pub fn connector(proxy_connector: ProxyConnector<HttpConnector>) -> Connector {
+        proxy_connector
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use super::transport::{Transport, TransportFuture, TransportRequest};
+
+/// Middleware around the requests `Pusher` sends, for cross-cutting concerns
+/// such as adding headers, rewriting hosts, retries or timeouts. Interceptors
+/// are added with `PusherBuilder::interceptor`, and run in the order they were
+/// added, each passing the request on to the `Next` one; the last passes it to
+/// the `Transport`.
+///
+/// Requests are already signed when they are intercepted. The signature covers
+/// the method, path, query and body, so an interceptor can add headers or
+/// change the scheme, host and port, but changing anything else will make the
+/// request fail authentication.
+///
+/// With the `tower` feature, `tower::Layer`s can be applied to a transport
+/// instead; see `pusher::TransportService`.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::PusherBuilder;
+/// use pusher::{Interceptor, Next, TransportFuture, TransportRequest};
+///
+/// struct TenantHeader(String);
+///
+/// impl Interceptor for TenantHeader {
+///     fn intercept<'a>(&'a self, mut request: TransportRequest, next: Next<'a>) -> TransportFuture<'a> {
+///         request.headers.push(("x-tenant-id".to_string(), self.0.clone()));
+///         next.run(request)
+///     }
+/// }
+///
+/// let pusher = PusherBuilder::new("id", "key", "secret")
+///   .interceptor(TenantHeader("acme".to_string()))
+///   .finalize();
+/// ```
+pub trait Interceptor: Send + Sync {
+    /// Handles a request, usually by passing it (or a modified copy) on with
+    /// `next.run`. The request can be retried by running a clone of `next`
+    /// more than once, or answered without calling `next` at all.
+    fn intercept<'a>(&'a self, request: TransportRequest, next: Next<'a>) -> TransportFuture<'a>;
+}
+
+/// The rest of the chain after an `Interceptor`: the interceptors after it,
+/// then the transport.
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    interceptors: &'a [Arc<dyn Interceptor>],
+    transport: &'a dyn Transport,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        interceptors: &'a [Arc<dyn Interceptor>],
+        transport: &'a dyn Transport,
+    ) -> Next<'a> {
+        Next {
+            interceptors,
+            transport,
+        }
+    }
+
+    /// Passes the request on to the next interceptor, or the transport if
+    /// there are no more.
+    pub fn run(self, request: TransportRequest) -> TransportFuture<'a> {
+        match self.interceptors.split_first() {
+            Some((interceptor, rest)) => {
+                interceptor.intercept(request, Next::new(rest, self.transport))
+            }
+            None => self.transport.send(request),
+        }
+    }
+}
@@ -1,30 +1,104 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+use super::util::SocketId;
 
 #[derive(Serialize)]
 pub struct TriggerEventData {
     pub name: String,
     pub channels: Vec<String>,
     pub data: String,
-    pub socket_id: Option<String>,
+    pub socket_id: Option<SocketId>,
 }
 
 /// When querying the state of Pusher channels, you can pass this in to specify
-/// options.
+/// options. Prefer `ChannelsQuery` and `ChannelQuery`, which validate the options
+/// before a request is made; this remains for options they do not cover.
 pub type QueryParameters = Vec<(String, String)>;
 
 /// Any event_ids returned by the HTTP API, if connected to certain clusters.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
 pub struct TriggeredEvents {
     /// For certain clusters, event_ids will be returned upon triggering.
     /// Otherwise, this value will be `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub event_ids: Option<HashMap<String, String>>,
+    /// If channel attributes were requested with the trigger, the state of
+    /// each channel the event was published to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<HashMap<String, Channel>>,
+}
+
+impl TriggeredEvents {
+    /// Adds the event_ids and channels of another trigger of the same event.
+    pub(crate) fn merge(&mut self, other: TriggeredEvents) {
+        if let Some(event_ids) = other.event_ids {
+            self.event_ids
+                .get_or_insert_with(HashMap::new)
+                .extend(event_ids);
+        }
+        if let Some(channels) = other.channels {
+            self.channels
+                .get_or_insert_with(HashMap::new)
+                .extend(channels);
+        }
+    }
+}
+
+/// An event to trigger as part of a batch, with `Pusher::trigger_batch` or a
+/// `PublisherHandle`. Unlike a single trigger, each event in a batch is
+/// triggered on one channel.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BatchEvent {
+    pub channel: String,
+    pub name: String,
+    /// The payload, as JSON.
+    pub data: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket_id: Option<SocketId>,
+}
+
+impl BatchEvent {
+    /// Creates an event with the given payload, which is serialized to JSON.
+    pub fn new<S: serde::Serialize>(channel: &str, event: &str, payload: S) -> BatchEvent {
+        BatchEvent {
+            channel: channel.to_string(),
+            name: event.to_string(),
+            data: serde_json::to_string(&payload).unwrap(),
+            socket_id: None,
+        }
+    }
+
+    /// Excludes the connection with the given `socket_id` from receiving the
+    /// event.
+    pub fn socket_id(mut self, socket_id: &SocketId) -> BatchEvent {
+        self.socket_id = Some(socket_id.clone());
+        self
+    }
+}
+
+#[derive(Serialize)]
+pub struct BatchEventData<'a> {
+    pub batch: &'a [BatchEvent],
+}
+
+/// The response to a batch trigger. If channel attributes were requested, it
+/// has the state of each event's channel, in the order of the events.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct TriggeredBatch {
+    pub batch: Vec<Channel>,
 }
 
 /// A list of channels returned by the API.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
 pub struct ChannelList {
-    pub channels: HashMap<String, Channel>, // something fishy in practice
+    /// The channels keyed by name. Each `Channel` only carries the attributes
+    /// requested with the `info` query parameter.
+    pub channels: HashMap<String, Channel>,
 }
 
 /// When authenticating presence-channels, this represents a particular member
@@ -37,37 +111,161 @@ pub struct Member<'a> {
     pub user_info: Option<HashMap<&'a str, &'a str>>,
 }
 
+/// The response to a client's request to subscribe to a private or presence
+/// channel, which your authentication endpoint should return as JSON.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::PusherBuilder;
+/// # let pusher = PusherBuilder::new("id", "key", "secret").finalize();
+/// let response = pusher.authorize_private_channel("private-foobar", "1234.1234").unwrap();
+/// assert!(response.auth.starts_with("key:"));
+/// let body = response.to_json();
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChannelAuthResponse {
+    /// The app key and the signature of the subscription, as `key:signature`.
+    pub auth: String,
+    /// For presence channels, the member, as JSON.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_data: Option<String>,
+    /// For encrypted channels, the base64-encoded key to decrypt their events
+    /// with, if an encryption master key was configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shared_secret: Option<String>,
+}
+
+impl ChannelAuthResponse {
+    /// Serializes the response, as the client expects it.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
 /// This is returned upon validating that a webhook is indeed from Pusher,
 /// carrying all the data received by that POST request.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Webhook {
     /// The timestamp of the webhook
     pub time_ms: i64,
-    /// The events received with the webhook
-    pub events: Vec<HashMap<String, String>>,
+    /// The events received with the webhook. Values are kept as JSON, as some
+    /// events, such as `subscription_count`, carry numbers.
+    pub events: Vec<HashMap<String, Value>>,
+}
+
+impl Webhook {
+    /// The `cache_miss` events carried by this webhook, which are sent when a
+    /// client subscribes to a cache channel that has no cached event.
+    pub fn cache_misses(&self) -> Vec<CacheMiss> {
+        self.events
+            .iter()
+            .filter(|event| event.get("name").and_then(Value::as_str) == Some("cache_miss"))
+            .filter_map(|event| {
+                event
+                    .get("channel")
+                    .and_then(Value::as_str)
+                    .map(|channel| CacheMiss {
+                        channel: channel.to_string(),
+                    })
+            })
+            .collect()
+    }
+}
+
+/// A `cache_miss` webhook event.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CacheMiss {
+    /// The cache channel that was subscribed to without a cached event
+    pub channel: String,
 }
 
 /// This represents the data received upon querying the state of a Pusher channel.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
 pub struct Channel {
     /// Is the channel occupied?
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub occupied: Option<bool>,
-    /// The number of users presently subscribed to the channel
-    pub user_count: Option<i32>,
+    /// For presence-channels, the number of distinct users presently subscribed
+    /// to the channel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_count: Option<u64>,
     /// For accounts with subscription-counting enabled, the number of users currently
     /// subscribed to the channel.
-    pub subscription_count: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_count: Option<u64>,
+    /// For cache channels queried with the `cache` info attribute, the cached
+    /// event, if there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<ChannelCache>,
+}
+
+/// The last event triggered on a cache channel, as held by Pusher.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChannelCache {
+    /// The payload of the cached event
+    pub data: String,
+    /// The number of seconds until the cached event expires
+    pub ttl: u64,
 }
 
 /// The list of users subscribed to a presence channel, as returned by the Pusher
 /// API.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
 pub struct ChannelUserList {
     pub users: Vec<ChannelUser>,
 }
 
+impl ChannelUserList {
+    /// Compares this snapshot of a presence channel with a `later` one,
+    /// returning the users who joined and left in between.
+    ///
+    /// **Example:**
+    ///
+    /// ```
+    /// # use pusher::ChannelUserList;
+    /// let before: ChannelUserList = serde_json::from_str(r#"{"users":[{"id":"red"},{"id":"blue"}]}"#).unwrap();
+    /// let after: ChannelUserList = serde_json::from_str(r#"{"users":[{"id":"blue"},{"id":"green"}]}"#).unwrap();
+    /// let diff = before.diff(&after);
+    /// assert_eq!(diff.joined[0].id, "green");
+    /// assert_eq!(diff.left[0].id, "red");
+    /// ```
+    pub fn diff(&self, later: &ChannelUserList) -> PresenceDiff {
+        let earlier_ids: HashSet<&str> = self.users.iter().map(|u| u.id.as_str()).collect();
+        let later_ids: HashSet<&str> = later.users.iter().map(|u| u.id.as_str()).collect();
+        PresenceDiff {
+            joined: later
+                .users
+                .iter()
+                .filter(|u| !earlier_ids.contains(u.id.as_str()))
+                .cloned()
+                .collect(),
+            left: self
+                .users
+                .iter()
+                .filter(|u| !later_ids.contains(u.id.as_str()))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
 /// A particular user who occupies a presence channel.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ChannelUser {
     pub id: String,
+    /// Any further attributes returned for the user by the API.
+    #[serde(flatten)]
+    pub info: HashMap<String, Value>,
+}
+
+/// The change in membership between two snapshots of a presence channel.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PresenceDiff {
+    /// Users present in the later snapshot but not the earlier one
+    pub joined: Vec<ChannelUser>,
+    /// Users present in the earlier snapshot but not the later one
+    pub left: Vec<ChannelUser>,
 }
@@ -36,20 +36,61 @@
 //!   }
 //! }
 //! ```
+//!
+//! If you are not running an async runtime, enable the `blocking` feature and
+//! use `pusher::blocking::PusherBuilder` instead; its methods return results
+//! directly.
 
 extern crate hyper;
 extern crate regex;
 extern crate serde;
 
+mod api;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod channel_query;
 mod client;
+mod clock;
+mod config;
+mod connector;
+mod interceptor;
 mod json_structures;
+mod observer;
+mod proxy;
+mod publisher;
+mod rate_limit;
 mod request;
 mod request_url;
 mod signature;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tracing")]
+mod trace;
+mod transport;
 mod util;
+mod verifier;
 
+pub use self::api::{ApiFuture, PusherApi};
+pub use self::channel_query::{ChannelInfo, ChannelQuery, ChannelsQuery};
 pub use self::client::{Pusher, PusherBuilder};
+pub use self::clock::{Clock, SystemClock};
+pub use self::connector::DefaultConnector;
+pub use self::interceptor::{Interceptor, Next};
 pub use self::json_structures::{
-    Channel, ChannelList, ChannelUser, ChannelUserList, Member, QueryParameters, TriggeredEvents,
+    BatchEvent, CacheMiss, Channel, ChannelAuthResponse, ChannelCache, ChannelList, ChannelUser,
+    ChannelUserList, Member, PresenceDiff, QueryParameters, TriggeredBatch, TriggeredEvents,
     Webhook,
 };
+#[cfg(feature = "metrics")]
+pub use self::observer::MetricsObserver;
+pub use self::observer::{ErrorClass, RequestInfo, RequestObserver, ResponseInfo};
+pub use self::proxy::{Proxy, ProxyConnector, ProxyStream};
+pub use self::publisher::{PublisherHandle, PublisherOptions};
+pub use self::rate_limit::RateLimit;
+pub use self::transport::{
+    BlockingTransport, Transport, TransportFuture, TransportRequest, TransportResponse,
+};
+#[cfg(feature = "tower")]
+pub use self::transport::{ServiceTransport, TransportService};
+pub use self::util::{ChannelType, EventName, SocketId};
+pub use self::verifier::RequestVerifier;
@@ -0,0 +1,388 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::request::{parse_response, RequestError};
+use super::transport::{TransportRequest, TransportResponse};
+
+/// Callbacks made before and after each request to the HTTP API, for
+/// collecting metrics such as latency, payload sizes and error rates.
+/// Observers are added with `PusherBuilder::observer`; both methods do nothing
+/// by default.
+///
+/// With the `metrics` feature, `pusher::MetricsObserver` records these through
+/// the `metrics` crate.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::PusherBuilder;
+/// use pusher::{RequestInfo, RequestObserver, ResponseInfo};
+///
+/// struct LogFailures;
+///
+/// impl RequestObserver for LogFailures {
+///     fn on_response(&self, request: &RequestInfo, response: &ResponseInfo) {
+///         if let Some(error) = response.error {
+///             eprintln!("{} {} failed: {:?}", request.method, request.path, error);
+///         }
+///     }
+/// }
+///
+/// let pusher = PusherBuilder::new("id", "key", "secret").observer(LogFailures).finalize();
+/// ```
+pub trait RequestObserver: Send + Sync {
+    /// Called before a request is sent.
+    fn on_request(&self, _request: &RequestInfo) {}
+
+    /// Called once a request has completed, successfully or not.
+    fn on_response(&self, _request: &RequestInfo, _response: &ResponseInfo) {}
+}
+
+/// A request to the HTTP API, as seen by a `RequestObserver`. The query
+/// string, which holds the request's signature, is not included.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestInfo {
+    pub method: String,
+    /// The path of the request, e.g. `/apps/123/events`.
+    pub path: String,
+    /// The name of the event being triggered, unless the request is not a
+    /// trigger or is a batch.
+    pub event: Option<String>,
+    /// The channels an event is triggered on, or the channel being queried.
+    pub channels: Vec<String>,
+    /// Whether the request triggers events, singly or in a batch.
+    pub trigger: bool,
+    /// The size of the request body in bytes.
+    pub payload_size: usize,
+}
+
+/// The outcome of a request to the HTTP API, as seen by a `RequestObserver`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResponseInfo {
+    /// The status of the response, unless none was received.
+    pub status: Option<u16>,
+    /// How long the request took, including reading the response.
+    pub latency: Duration,
+    /// The kind of error, if the request failed.
+    pub error: Option<ErrorClass>,
+}
+
+/// The kinds of error a request can fail with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// No response was received, e.g. because the connection failed.
+    Transport,
+    /// The response had a 4xx status.
+    Client,
+    /// The response had a 5xx status, or another unexpected one.
+    Server,
+    /// The response was successful, but its body could not be parsed.
+    InvalidResponse,
+}
+
+impl ErrorClass {
+    /// A short name for the error class, suitable as a metric label.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ErrorClass::Transport => "transport",
+            ErrorClass::Client => "client",
+            ErrorClass::Server => "server",
+            ErrorClass::InvalidResponse => "invalid_response",
+        }
+    }
+}
+
+/// What a request is for, beyond its method and path.
+#[derive(Clone, Copy, Default)]
+pub struct Target<'a> {
+    pub event: Option<&'a str>,
+    pub channels: &'a [String],
+    pub trigger: bool,
+}
+
+impl<'a> Target<'a> {
+    /// A trigger of `event` on the given channels.
+    pub fn event(event: &'a str, channels: &'a [String]) -> Target<'a> {
+        Target {
+            event: Some(event),
+            channels,
+            trigger: true,
+        }
+    }
+
+    /// A batch trigger, with the channel of each event.
+    pub fn batch(channels: &'a [String]) -> Target<'a> {
+        Target {
+            event: None,
+            channels,
+            trigger: true,
+        }
+    }
+
+    /// A request about the given channels, rather than a trigger.
+    pub fn channels(channels: &'a [String]) -> Target<'a> {
+        Target {
+            event: None,
+            channels,
+            trigger: false,
+        }
+    }
+}
+
+/// A request in flight, whose observers are told of its outcome when it is
+/// finished.
+pub struct Observation<'a> {
+    observers: &'a [Arc<dyn RequestObserver>],
+    request: Option<RequestInfo>,
+    start: Instant,
+}
+
+impl<'a> Observation<'a> {
+    pub fn start(
+        observers: &'a [Arc<dyn RequestObserver>],
+        request: &TransportRequest,
+        target: Target,
+    ) -> Observation<'a> {
+        // Only pay for the copies if someone is listening.
+        let request = if observers.is_empty() {
+            None
+        } else {
+            let info = RequestInfo {
+                method: request.method.clone(),
+                path: request.url.path().to_string(),
+                event: target.event.map(str::to_string),
+                channels: target.channels.to_vec(),
+                trigger: target.trigger,
+                payload_size: request.body.as_ref().map_or(0, String::len),
+            };
+            for observer in observers {
+                observer.on_request(&info);
+            }
+            Some(info)
+        };
+        Observation {
+            observers,
+            request,
+            start: Instant::now(),
+        }
+    }
+
+    /// Parses the response, and reports the outcome to the observers.
+    pub fn finish<R>(self, response: Result<TransportResponse, String>) -> Result<R, RequestError>
+    where
+        R: serde::de::DeserializeOwned,
+    {
+        let status = response.as_ref().ok().map(|response| response.status);
+        let result = response.and_then(parse_response).map_err(|message| {
+            let class = match status {
+                None => ErrorClass::Transport,
+                Some(200) => ErrorClass::InvalidResponse,
+                Some(400..=499) => ErrorClass::Client,
+                Some(_) => ErrorClass::Server,
+            };
+            RequestError {
+                message,
+                class: Some(class),
+                status,
+            }
+        });
+        if let Some(request) = self.request {
+            let response = ResponseInfo {
+                status,
+                latency: self.start.elapsed(),
+                error: result.as_ref().err().and_then(|err| err.class),
+            };
+            for observer in self.observers {
+                observer.on_response(&request, &response);
+            }
+        }
+        result
+    }
+}
+
+/// A `RequestObserver` that records metrics with the `metrics` crate, for
+/// whichever recorder the application has installed:
+///
+/// * `pusher_requests_total`, a counter labelled with the `method`, the
+///   `channel_type` and the `outcome`, which is `success` or an `ErrorClass`
+/// * `pusher_request_duration_seconds`, a histogram labelled with the `method`
+///   and the `channel_type`
+/// * `pusher_payload_bytes`, a histogram of the body sizes of triggers,
+///   labelled with the `channel_type`
+///
+/// The channel type is the channel's prefix without its trailing dash, such as
+/// `private` or `presence-cache`, or `public` for other channels. A request on
+/// channels of several types is counted once for each type, and requests on no
+/// channel at all, such as listing channels, have the type `none`.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::{MetricsObserver, PusherBuilder};
+/// let pusher = PusherBuilder::new("id", "key", "secret")
+///   .observer(MetricsObserver::new())
+///   .finalize();
+/// ```
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsObserver;
+
+#[cfg(feature = "metrics")]
+impl MetricsObserver {
+    pub fn new() -> MetricsObserver {
+        MetricsObserver
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl RequestObserver for MetricsObserver {
+    fn on_response(&self, request: &RequestInfo, response: &ResponseInfo) {
+        let outcome = response.error.map_or("success", |error| error.as_str());
+        for channel_type in channel_type_labels(&request.channels) {
+            metrics::counter!(
+                "pusher_requests_total",
+                "method" => request.method.clone(),
+                "channel_type" => channel_type,
+                "outcome" => outcome,
+            )
+            .increment(1);
+            metrics::histogram!(
+                "pusher_request_duration_seconds",
+                "method" => request.method.clone(),
+                "channel_type" => channel_type,
+            )
+            .record(response.latency.as_secs_f64());
+            if request.trigger {
+                metrics::histogram!("pusher_payload_bytes", "channel_type" => channel_type)
+                    .record(request.payload_size as f64);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn channel_type_labels(channels: &[String]) -> Vec<&'static str> {
+    let mut labels = Vec::new();
+    for channel in channels {
+        let label = match super::util::ChannelType::from_name(channel).prefix() {
+            "" => "public",
+            prefix => prefix.trim_end_matches('-'),
+        };
+        if !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+    if labels.is_empty() {
+        labels.push("none");
+    }
+    labels
+}
+
+#[test]
+fn test_error_classes() {
+    let request = TransportRequest::new("GET", url::Url::parse("http://a/b").unwrap(), None);
+    let body = |status: u16, body: &str| {
+        Ok(TransportResponse {
+            status,
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        })
+    };
+    let responses = vec![
+        (body(200, "{}"), None),
+        (body(200, "nope"), Some(ErrorClass::InvalidResponse)),
+        (body(404, ""), Some(ErrorClass::Client)),
+        (body(429, ""), Some(ErrorClass::Client)),
+        (body(503, ""), Some(ErrorClass::Server)),
+        (body(302, ""), Some(ErrorClass::Server)),
+        (
+            Err("Error: refused".to_string()),
+            Some(ErrorClass::Transport),
+        ),
+    ];
+
+    struct Record(std::sync::Mutex<Vec<ResponseInfo>>);
+    impl RequestObserver for Record {
+        fn on_response(&self, _request: &RequestInfo, response: &ResponseInfo) {
+            self.0.lock().unwrap().push(*response);
+        }
+    }
+    let record = Arc::new(Record(std::sync::Mutex::new(Vec::new())));
+    let observers: Vec<Arc<dyn RequestObserver>> = vec![record.clone()];
+    for (response, _) in &responses {
+        let observation = Observation::start(&observers, &request, Target::default());
+        let _: Result<serde_json::Value, _> = observation.finish(response.clone());
+    }
+
+    let seen = record.0.lock().unwrap();
+    for (info, (response, error)) in seen.iter().zip(&responses) {
+        assert_eq!(info.error, *error);
+        assert_eq!(info.status, response.as_ref().ok().map(|r| r.status));
+    }
+    assert_eq!(seen.len(), responses.len())
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn test_metrics_observer() {
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let request = RequestInfo {
+        method: "POST".to_string(),
+        path: "/apps/1/events".to_string(),
+        event: Some("my_event".to_string()),
+        channels: vec![
+            "private-a".to_string(),
+            "private-b".to_string(),
+            "presence-cache-c".to_string(),
+        ],
+        trigger: true,
+        payload_size: 100,
+    };
+    let response = ResponseInfo {
+        status: Some(500),
+        latency: Duration::from_millis(250),
+        error: Some(ErrorClass::Server),
+    };
+    metrics::with_local_recorder(&recorder, || {
+        MetricsObserver::new().on_response(&request, &response)
+    });
+
+    let mut counters = Vec::new();
+    let mut payload_sizes = Vec::new();
+    for (key, _, _, value) in snapshotter.snapshot().into_vec() {
+        let key = key.key();
+        let channel_type = key
+            .labels()
+            .find(|label| label.key() == "channel_type")
+            .unwrap()
+            .value()
+            .to_string();
+        match (key.name(), value) {
+            ("pusher_requests_total", DebugValue::Counter(count)) => {
+                let outcome = key.labels().find(|label| label.key() == "outcome");
+                assert_eq!(outcome.unwrap().value(), "server");
+                counters.push((channel_type, count));
+            }
+            ("pusher_payload_bytes", DebugValue::Histogram(values)) => {
+                payload_sizes.push((channel_type, values[0].into_inner()));
+            }
+            ("pusher_request_duration_seconds", DebugValue::Histogram(values)) => {
+                assert_eq!(values[0].into_inner(), 0.25);
+            }
+            (name, _) => panic!("unexpected metric {}", name),
+        }
+    }
+    counters.sort();
+    assert_eq!(
+        counters,
+        vec![
+            ("presence-cache".to_string(), 1),
+            ("private".to_string(), 1)
+        ]
+    );
+    assert_eq!(payload_sizes.len(), 2);
+    assert!(payload_sizes.iter().all(|(_, size)| *size == 100.0))
+}
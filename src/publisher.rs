@@ -0,0 +1,286 @@
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::time::Instant;
+
+use super::client::Pusher;
+use super::json_structures::BatchEvent;
+use super::transport::Transport;
+use super::util::validate_batch_event;
+
+// The API accepts at most this many events in a batch.
+const MAX_BATCH_SIZE: usize = 10;
+
+type ErrorCallback = Arc<dyn Fn(&[BatchEvent], &str) + Send + Sync>;
+
+/// Options for a `PublisherHandle`, created with `Pusher::publisher`.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::PublisherOptions;
+/// # use std::time::Duration;
+/// let options = PublisherOptions::new()
+///   .capacity(10_000)
+///   .linger(Duration::from_millis(20))
+///   .on_error(|events, err| eprintln!("Dropped {} events: {}", events.len(), err));
+/// ```
+#[derive(Clone)]
+pub struct PublisherOptions {
+    capacity: usize,
+    max_batch_size: usize,
+    linger: Duration,
+    max_attempts: u32,
+    retry_backoff: Duration,
+    on_error: Option<ErrorCallback>,
+}
+
+impl PublisherOptions {
+    /// The defaults: a queue of 1024 events, batches of up to 10 events sent
+    /// at most 50ms after their first event was published, and up to 3
+    /// attempts per batch, 100ms apart and doubling.
+    pub fn new() -> PublisherOptions {
+        PublisherOptions {
+            capacity: 1024,
+            max_batch_size: MAX_BATCH_SIZE,
+            linger: Duration::from_millis(50),
+            max_attempts: 3,
+            retry_backoff: Duration::from_millis(100),
+            on_error: None,
+        }
+    }
+
+    /// Sets how many events can wait in the queue before `publish` waits for
+    /// room and `try_publish` fails.
+    pub fn capacity(mut self, capacity: usize) -> PublisherOptions {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets how many events are sent in each batch, up to the API's limit of
+    /// 10.
+    pub fn max_batch_size(mut self, max_batch_size: usize) -> PublisherOptions {
+        self.max_batch_size = max_batch_size.clamp(1, MAX_BATCH_SIZE);
+        self
+    }
+
+    /// Sets how long a batch waits for more events after its first, before it
+    /// is sent anyway.
+    pub fn linger(mut self, linger: Duration) -> PublisherOptions {
+        self.linger = linger;
+        self
+    }
+
+    /// Sets how many times a batch is sent in all, if it fails without a
+    /// response or with a server error, and how long to wait before the first
+    /// retry. The wait doubles with each retry.
+    pub fn retries(mut self, max_attempts: u32, backoff: Duration) -> PublisherOptions {
+        self.max_attempts = max_attempts.max(1);
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Sets a function to call with the events of each batch that could not be
+    /// sent, and the error of its last attempt. Without one, such events are
+    /// dropped silently.
+    pub fn on_error<F>(mut self, on_error: F) -> PublisherOptions
+    where
+        F: Fn(&[BatchEvent], &str) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(on_error));
+        self
+    }
+}
+
+impl Default for PublisherOptions {
+    fn default() -> PublisherOptions {
+        PublisherOptions::new()
+    }
+}
+
+impl fmt::Debug for PublisherOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PublisherOptions")
+            .field("capacity", &self.capacity)
+            .field("max_batch_size", &self.max_batch_size)
+            .field("linger", &self.linger)
+            .field("max_attempts", &self.max_attempts)
+            .field("retry_backoff", &self.retry_backoff)
+            .field("on_error", &self.on_error.is_some())
+            .finish()
+    }
+}
+
+enum Command {
+    Publish(BatchEvent),
+    Flush(oneshot::Sender<()>),
+    Shutdown,
+}
+
+/// A fire-and-forget queue of events, which a background task sends with
+/// `Pusher::trigger_batch`, coalescing them into batches. Create one with
+/// `Pusher::publisher`; clones share the same queue and task.
+///
+/// Events are sent in the order they were published. Batches that fail are
+/// retried, then passed to the `on_error` callback of the `PublisherOptions`.
+/// The task stops once `shutdown` is called, or all the handles are dropped,
+/// after sending the events still in the queue.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::{BatchEvent, PublisherOptions, PusherBuilder};
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// # let pusher = PusherBuilder::new("id", "key", "secret").finalize();
+/// let publisher = pusher.publisher(PublisherOptions::new());
+/// publisher.try_publish(BatchEvent::new("test_channel", "my_event", "hello")).unwrap();
+/// publisher.shutdown().await;
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PublisherHandle {
+    sender: mpsc::Sender<Command>,
+    stopped: watch::Receiver<()>,
+}
+
+impl PublisherHandle {
+    pub(crate) fn spawn<T>(pusher: Pusher<T>, options: PublisherOptions) -> PublisherHandle
+    where
+        T: Transport + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(options.capacity);
+        let (stopped_sender, stopped) = watch::channel(());
+        tokio::spawn(async move {
+            run(pusher, options, receiver).await;
+            // Wakes anyone waiting in `shutdown`.
+            drop(stopped_sender);
+        });
+        PublisherHandle { sender, stopped }
+    }
+
+    /// Adds an event to the queue, waiting for room if it is full. Fails if
+    /// the event is invalid or the publisher has shut down.
+    pub async fn publish(&self, event: BatchEvent) -> Result<(), String> {
+        validate_batch_event(&event)?;
+        self.sender
+            .send(Command::Publish(event))
+            .await
+            .map_err(|_| shut_down())
+    }
+
+    /// Adds an event to the queue without waiting. Fails if the event is
+    /// invalid, the queue is full or the publisher has shut down.
+    pub fn try_publish(&self, event: BatchEvent) -> Result<(), String> {
+        validate_batch_event(&event)?;
+        self.sender
+            .try_send(Command::Publish(event))
+            .map_err(|err| match err {
+                mpsc::error::TrySendError::Full(_) => "Publisher queue is full".to_string(),
+                mpsc::error::TrySendError::Closed(_) => shut_down(),
+            })
+    }
+
+    /// Sends the events published so far without waiting for their batches
+    /// to fill, and returns once they have been sent, or passed to the
+    /// `on_error` callback.
+    pub async fn flush(&self) -> Result<(), String> {
+        let (done, flushed) = oneshot::channel();
+        self.sender
+            .send(Command::Flush(done))
+            .await
+            .map_err(|_| shut_down())?;
+        flushed.await.map_err(|_| shut_down())
+    }
+
+    /// Stops accepting events, and returns once those already in the queue
+    /// have been sent and the background task has stopped.
+    pub async fn shutdown(&self) {
+        // The queue may be closed already, by another handle.
+        let _ = self.sender.send(Command::Shutdown).await;
+        let mut stopped = self.stopped.clone();
+        while stopped.changed().await.is_ok() {}
+    }
+}
+
+impl fmt::Debug for PublisherHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PublisherHandle").finish_non_exhaustive()
+    }
+}
+
+fn shut_down() -> String {
+    "Publisher is shut down".to_string()
+}
+
+async fn run<T: Transport>(
+    pusher: Pusher<T>,
+    options: PublisherOptions,
+    mut receiver: mpsc::Receiver<Command>,
+) {
+    let mut batch = Vec::with_capacity(options.max_batch_size);
+    let mut deadline = Instant::now();
+    loop {
+        let command = if batch.is_empty() {
+            receiver.recv().await
+        } else {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(command) => command,
+                Err(_) => {
+                    send_batch(&pusher, &options, mem::take(&mut batch)).await;
+                    continue;
+                }
+            }
+        };
+        match command {
+            Some(Command::Publish(event)) => {
+                if batch.is_empty() {
+                    deadline = Instant::now() + options.linger;
+                }
+                batch.push(event);
+                if batch.len() >= options.max_batch_size {
+                    send_batch(&pusher, &options, mem::take(&mut batch)).await;
+                }
+            }
+            Some(Command::Flush(done)) => {
+                if !batch.is_empty() {
+                    send_batch(&pusher, &options, mem::take(&mut batch)).await;
+                }
+                let _ = done.send(());
+            }
+            // Closing the queue lets the events already in it be received,
+            // then ends the loop.
+            Some(Command::Shutdown) => receiver.close(),
+            None => break,
+        }
+    }
+    if !batch.is_empty() {
+        send_batch(&pusher, &options, batch).await;
+    }
+}
+
+async fn send_batch<T: Transport>(
+    pusher: &Pusher<T>,
+    options: &PublisherOptions,
+    events: Vec<BatchEvent>,
+) {
+    let mut attempt = 1;
+    let mut backoff = options.retry_backoff;
+    loop {
+        let err = match pusher._trigger_batch(events.clone()).await {
+            Ok(_) => return,
+            Err(err) => err,
+        };
+        if attempt >= options.max_attempts || !err.is_retryable() {
+            if let Some(ref on_error) = options.on_error {
+                on_error(&events, &err.message);
+            }
+            return;
+        }
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
@@ -0,0 +1,282 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::config::Config;
+use super::observer::Target;
+use super::transport::TransportResponse;
+
+// How many times a trigger is sent in all, if it keeps being rate limited.
+const MAX_ATTEMPTS: u32 = 3;
+// Longer waits are not retried; the caller is told of the 429 instead.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// A client-side limit on the rate at which messages are triggered, as a token
+/// bucket that refills at `messages_per_second` and holds up to `burst`
+/// messages. A trigger on several channels counts as one message per channel.
+/// Triggers that would exceed the limit are delayed until they fit, or, with
+/// `reject()`, fail without being sent.
+///
+/// When a trigger gets a `429 Too Many Requests` response with a `Retry-After`
+/// header (in seconds), later triggers are held back for that long too. In
+/// the default, delaying mode, the trigger itself is then retried, as it is
+/// without a limit; see `PusherBuilder::rate_limit`.
+///
+/// Other requests, such as channel queries, are not limited. Delays use Tokio's
+/// timer, so the asynchronous client must run on a Tokio runtime with the time
+/// driver enabled.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::{PusherBuilder, RateLimit};
+/// let pusher = PusherBuilder::new("id", "key", "secret")
+///   .rate_limit(RateLimit::new(10, 100))
+///   .finalize();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    messages_per_second: f64,
+    burst: u32,
+    reject: bool,
+}
+
+impl RateLimit {
+    /// Limits triggers to `messages_per_second` on average, in bursts of up to
+    /// `burst` messages.
+    pub fn new(messages_per_second: u32, burst: u32) -> RateLimit {
+        RateLimit {
+            messages_per_second: f64::from(messages_per_second.max(1)),
+            burst: burst.max(1),
+            reject: false,
+        }
+    }
+
+    /// Makes triggers that would exceed the limit fail with an error, rather
+    /// than wait.
+    pub fn reject(mut self) -> RateLimit {
+        self.reject = true;
+        self
+    }
+}
+
+/// The state of a `RateLimit`, shared by the clones of a `Pusher`.
+pub struct RateLimiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> RateLimiter {
+        RateLimiter {
+            limit,
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(limit.burst),
+                updated: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Takes tokens for a trigger of `messages` messages, returning how long
+    /// to wait before sending it.
+    pub fn acquire(&self, messages: usize) -> Result<Duration, String> {
+        self.acquire_at(messages, Instant::now())
+    }
+
+    fn acquire_at(&self, messages: usize, now: Instant) -> Result<Duration, String> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.limit.messages_per_second)
+            .min(f64::from(self.limit.burst));
+        bucket.updated = now;
+
+        let paused = bucket
+            .paused_until
+            .map_or(Duration::ZERO, |until| until.saturating_duration_since(now));
+        let messages = messages as f64;
+        if self.limit.reject {
+            if !paused.is_zero() || bucket.tokens < messages {
+                return Err("Rate limit exceeded".to_string());
+            }
+            bucket.tokens -= messages;
+            return Ok(Duration::ZERO);
+        }
+
+        // Tokens may go negative, reserving the next ones for this trigger.
+        bucket.tokens -= messages;
+        let refill = if bucket.tokens < 0.0 {
+            Duration::from_secs_f64(-bucket.tokens / self.limit.messages_per_second)
+        } else {
+            Duration::ZERO
+        };
+        Ok(paused.max(refill))
+    }
+
+    fn pause_at(&self, delay: Duration, now: Instant) {
+        let mut bucket = self.bucket.lock().unwrap();
+        let until = now + delay;
+        bucket.paused_until = Some(
+            bucket
+                .paused_until
+                .map_or(until, |paused| paused.max(until)),
+        );
+    }
+}
+
+/// The rate limiting of a single request, shared by the asynchronous and
+/// blocking clients, which only differ in how they wait.
+///
+/// Triggers take their tokens from the `RateLimiter`, if there is one, and
+/// those answered with `429 Too Many Requests` are retried after the response's
+/// `Retry-After` delay, up to `MAX_ATTEMPTS` attempts in all and unless the
+/// limiter rejects rather than delays. Other requests are sent once, as is.
+pub struct Throttle<'a> {
+    limiter: Option<&'a RateLimiter>,
+    retries: bool,
+    attempt: u32,
+}
+
+impl<'a> Throttle<'a> {
+    /// Starts throttling a request, returning how long to wait before sending
+    /// it.
+    pub fn start(config: &'a Config, target: &Target) -> Result<(Throttle<'a>, Duration), String> {
+        if !target.trigger {
+            let throttle = Throttle {
+                limiter: None,
+                retries: false,
+                attempt: 1,
+            };
+            return Ok((throttle, Duration::ZERO));
+        }
+        let limiter = config.rate_limiter.as_deref();
+        let wait = match limiter {
+            Some(limiter) => limiter.acquire(target.channels.len())?,
+            None => Duration::ZERO,
+        };
+        let throttle = Throttle {
+            limiter,
+            retries: !limiter.is_some_and(|limiter| limiter.limit.reject),
+            attempt: 1,
+        };
+        Ok((throttle, wait))
+    }
+
+    /// The number of the attempt about to be sent, starting at 1.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Whether the attempt about to be sent may be retried, in which case the
+    /// request must be kept.
+    pub fn may_retry(&self) -> bool {
+        self.retries && self.attempt < MAX_ATTEMPTS
+    }
+
+    /// Handles the response to an attempt, returning how long to wait before
+    /// retrying it, if it should be.
+    pub fn retry_delay(
+        &mut self,
+        response: &Result<TransportResponse, String>,
+    ) -> Option<Duration> {
+        let delay = match response {
+            Ok(response) if response.status == 429 => retry_after(response)?,
+            _ => return None,
+        };
+        if let Some(limiter) = self.limiter {
+            limiter.pause_at(delay, Instant::now());
+        }
+        if !self.may_retry() || delay > MAX_RETRY_AFTER {
+            return None;
+        }
+        self.attempt += 1;
+        Some(delay)
+    }
+}
+
+fn retry_after(response: &TransportResponse) -> Option<Duration> {
+    let seconds: u64 = response.header("retry-after")?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+#[test]
+fn test_token_bucket_delays() {
+    let limiter = RateLimiter::new(RateLimit::new(10, 5));
+    let start = Instant::now();
+    assert_eq!(limiter.acquire_at(3, start), Ok(Duration::ZERO));
+    assert_eq!(limiter.acquire_at(2, start), Ok(Duration::ZERO));
+    // The bucket is empty, so the next message waits for a token to refill.
+    assert_eq!(limiter.acquire_at(1, start), Ok(Duration::from_millis(100)));
+    // Later ones queue up behind it, each channel counting as a message.
+    assert_eq!(limiter.acquire_at(4, start), Ok(Duration::from_millis(500)));
+    let later = start + Duration::from_secs(1);
+    assert_eq!(limiter.acquire_at(5, later), Ok(Duration::ZERO));
+}
+
+#[test]
+fn test_token_bucket_rejects() {
+    let limiter = RateLimiter::new(RateLimit::new(10, 5).reject());
+    let start = Instant::now();
+    assert_eq!(limiter.acquire_at(5, start), Ok(Duration::ZERO));
+    assert_eq!(
+        limiter.acquire_at(1, start),
+        Err("Rate limit exceeded".to_string())
+    );
+    let later = start + Duration::from_millis(200);
+    assert_eq!(limiter.acquire_at(2, later), Ok(Duration::ZERO));
+    assert!(limiter
+        .acquire_at(6, later + Duration::from_secs(10))
+        .is_err());
+}
+
+#[test]
+fn test_retry_after() {
+    let response = |status: u16, retry_after: &str| {
+        Ok(TransportResponse {
+            status,
+            headers: vec![("retry-after".to_string(), retry_after.to_string())],
+            body: Vec::new(),
+        })
+    };
+    let throttle = |limiter| Throttle {
+        limiter,
+        retries: !limiter.is_some_and(|limiter: &RateLimiter| limiter.limit.reject),
+        attempt: 1,
+    };
+
+    let limiter = RateLimiter::new(RateLimit::new(10, 5));
+    let mut trigger = throttle(Some(&limiter));
+    assert_eq!(trigger.retry_delay(&response(200, "2")), None);
+    assert_eq!(trigger.retry_delay(&response(429, "soon")), None);
+    assert_eq!(trigger.retry_delay(&response(429, "3600")), None);
+    assert_eq!(
+        trigger.retry_delay(&response(429, "2")),
+        Some(Duration::from_secs(2))
+    );
+    assert_eq!(trigger.attempt(), 2);
+    assert!(trigger.retry_delay(&response(429, "2")).is_some());
+    assert!(!trigger.may_retry());
+    assert_eq!(trigger.retry_delay(&response(429, "2")), None);
+
+    // Later triggers wait out the longest pause.
+    let wait = limiter.acquire(1).unwrap();
+    assert!(wait > Duration::from_secs(3590), "{:?}", wait);
+
+    // Without a limiter, the trigger is still retried.
+    assert_eq!(
+        throttle(None).retry_delay(&response(429, "2")),
+        Some(Duration::from_secs(2))
+    );
+
+    let limiter = RateLimiter::new(RateLimit::new(10, 5).reject());
+    assert_eq!(
+        throttle(Some(&limiter)).retry_delay(&response(429, "2")),
+        None
+    );
+    assert!(limiter.acquire(1).is_err());
+}
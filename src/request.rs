@@ -1,45 +1,152 @@
-use bytes::Buf;
-use hyper::body;
-use hyper::client::connect::Connect;
-use hyper::header::CONTENT_TYPE;
-use hyper::{Body, Client, StatusCode, Uri};
-use std::io::Read;
-use std::str::FromStr;
-
-pub async fn send_request<C, T>(
-    client: &Client<C>,
-    method: &str,
-    request_url: url::Url,
-    data: Option<String>,
-) -> Result<T, String>
+use hyper::header::PROXY_AUTHORIZATION;
+use hyper::StatusCode;
+
+use super::config::Config;
+use super::interceptor::Next;
+use super::observer::{ErrorClass, Observation, Target};
+use super::rate_limit::Throttle;
+#[cfg(feature = "tracing")]
+use super::trace;
+use super::transport::{Transport, TransportRequest, TransportResponse};
+
+/// The error of a request, with how it failed if it was sent, so that callers
+/// can tell whether it is worth sending again. The public API only returns its
+/// message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestError {
+    pub message: String,
+    /// How the request failed, unless it failed before it was sent.
+    pub class: Option<ErrorClass>,
+    /// The status of the response, if one was received.
+    pub status: Option<u16>,
+}
+
+impl RequestError {
+    /// Whether the request may succeed if it is sent again: no response was
+    /// received, or the API was unavailable or rate limited.
+    pub fn is_retryable(&self) -> bool {
+        match (self.class, self.status) {
+            (Some(ErrorClass::Transport), _) => true,
+            (_, Some(status)) => status == 429 || status >= 500,
+            _ => false,
+        }
+    }
+}
+
+// Errors raised before a request is sent, such as validation errors.
+impl From<String> for RequestError {
+    fn from(message: String) -> RequestError {
+        RequestError {
+            message,
+            class: None,
+            status: None,
+        }
+    }
+}
+
+impl From<RequestError> for String {
+    fn from(err: RequestError) -> String {
+        err.message
+    }
+}
+
+pub async fn send_request<T, R>(
+    transport: &T,
+    config: &Config,
+    target: Target<'_>,
+    request: TransportRequest,
+) -> Result<R, RequestError>
 where
-    C: Connect + Clone + Send + Sync + 'static,
-    T: serde::de::DeserializeOwned,
+    T: Transport,
+    R: serde::de::DeserializeOwned,
 {
-    let request_uri: Uri = FromStr::from_str(request_url.as_str()).unwrap();
-    let request_builder = hyper::Request::builder()
-        .method(method)
-        .uri(request_uri)
-        .header(CONTENT_TYPE, "application/json");
-    let request = match data {
-        Some(body) => request_builder.body(Body::from(body)),
-        None => request_builder.body(Body::empty()),
+    let (mut throttle, wait) = Throttle::start(config, &target)?;
+    let mut request = request;
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+        config.resign(&mut request);
     }
-    .unwrap();
-
-    let response = client.request(request).await.unwrap();
-    let status = response.status();
-    let mut body_reader = body::aggregate(response).await.unwrap().reader();
 
-    match status {
-        StatusCode::OK => {
-            let body = serde_json::from_reader(body_reader).unwrap();
-            Ok(body)
+    let observation = Observation::start(&config.observers, &request, target);
+    let response = loop {
+        let retry = throttle.may_retry().then(|| request.clone());
+        let response = send_attempt(transport, config, request, throttle.attempt()).await;
+        match (retry, throttle.retry_delay(&response)) {
+            (Some(retry), Some(delay)) => {
+                tokio::time::sleep(delay).await;
+                request = retry;
+                config.resign(&mut request);
+            }
+            _ => break response,
         }
-        _ => {
-            let mut body = String::new();
-            body_reader.read_to_string(&mut body).unwrap();
-            Err(format!("Error: {}. {}", status, body))
+    };
+    observation.finish(response)
+}
+
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+async fn send_attempt<T: Transport>(
+    transport: &T,
+    config: &Config,
+    mut request: TransportRequest,
+    attempt: u32,
+) -> Result<TransportResponse, String> {
+    let _permit = match config.in_flight {
+        Some(ref in_flight) => Some(
+            in_flight
+                .acquire()
+                .await
+                .map_err(|err| format!("Error: {}", err))?,
+        ),
+        None => None,
+    };
+    if let Some(ref proxy) = config.proxy {
+        let uri = request.url.as_str().parse().ok();
+        if let Some(authorization) = uri.and_then(|uri| proxy.authorization(&uri)) {
+            request
+                .headers
+                .push((PROXY_AUTHORIZATION.to_string(), authorization.to_string()));
         }
     }
+    #[cfg(feature = "tracing")]
+    let span = trace::request_span(&request, attempt);
+    let response = Next::new(&config.interceptors, transport).run(request);
+    #[cfg(feature = "tracing")]
+    let response = trace::record(span, response);
+    response.await
+}
+
+pub fn parse_response<R>(response: TransportResponse) -> Result<R, String>
+where
+    R: serde::de::DeserializeOwned,
+{
+    match StatusCode::from_u16(response.status) {
+        Ok(StatusCode::OK) => serde_json::from_slice(&response.body)
+            .map_err(|err| format!("Error: invalid response body. {}", err)),
+        Ok(status) => Err(format!(
+            "Error: {}. {}",
+            status,
+            String::from_utf8_lossy(&response.body)
+        )),
+        Err(_) => Err(format!(
+            "Error: {}. {}",
+            response.status,
+            String::from_utf8_lossy(&response.body)
+        )),
+    }
+}
+
+#[test]
+fn test_is_retryable() {
+    let error = |class, status| RequestError {
+        message: String::new(),
+        class: Some(class),
+        status,
+    };
+    assert!(error(ErrorClass::Transport, None).is_retryable());
+    assert!(error(ErrorClass::Server, Some(503)).is_retryable());
+    assert!(error(ErrorClass::Client, Some(429)).is_retryable());
+    assert!(!error(ErrorClass::Client, Some(400)).is_retryable());
+    assert!(!error(ErrorClass::Server, Some(302)).is_retryable());
+    assert!(!error(ErrorClass::InvalidResponse, Some(200)).is_retryable());
+    assert!(!RequestError::from("Data must be smaller than 10kb".to_string()).is_retryable());
 }
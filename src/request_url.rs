@@ -1,17 +1,32 @@
-use std::time::SystemTime;
 use url::form_urlencoded::Serializer;
 
 use super::json_structures::*;
 use super::signature::*;
 
-const AUTH_VERSION: &'static str = "1.0";
+const AUTH_VERSION: &str = "1.0";
+
+/// The string a request's `auth_signature` is the HMAC of, as described in
+/// https://pusher.com/docs/channels/library_auth_reference/rest-api#generating-authentication-signatures
+///
+/// The query parameters have their keys lowercased and are sorted by key, with
+/// `auth_signature` excluded. Values are not URL encoded.
+pub fn string_to_sign(method: &str, path: &str, query_pairs: &[(&str, &str)]) -> String {
+    let query_string = canonical_pairs(query_pairs)
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{}\n{}\n{}", method.to_uppercase(), path, query_string)
+}
 
-pub fn timestamp() -> String {
-    SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        .to_string()
+fn canonical_pairs<'a>(query_pairs: &[(&str, &'a str)]) -> Vec<(String, &'a str)> {
+    let mut pairs: Vec<(String, &str)> = query_pairs
+        .iter()
+        .map(|&(k, v)| (k.to_lowercase(), v))
+        .filter(|(k, _)| k != "auth_signature")
+        .collect();
+    pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    pairs
 }
 
 pub fn build_query(
@@ -36,42 +51,23 @@ pub fn build_query(
         query_pairs.push(("body_md5", &body_md5));
     }
 
-    let params = match query_parameters {
-        Some(params) => params,
-        None => Vec::new(),
-    };
+    let params = query_parameters.unwrap_or_default();
     for (k, v) in &params {
         query_pairs.push((k.as_str(), v.as_str()));
     }
 
-    // query_pairs.sort_by(|&(a, _), &(b, _)| { a.cmp(b) });
-
-    // Build the query string to sign by hand because we don't want it URL encoded
-    let mut query_string_to_sign = String::new();
-    let mut first = true;
-    for &(k, v) in &query_pairs {
-        if first {
-            first = false;
-        } else {
-            query_string_to_sign.push_str("&");
-        }
-        query_string_to_sign.push_str(k);
-        query_string_to_sign.push_str("=");
-        query_string_to_sign.push_str(v);
-    }
-
-    let to_sign = format!("{}\n{}\n{}", method, path, query_string_to_sign);
-    let auth_signature = create_auth_signature(&to_sign, &secret);
+    let to_sign = string_to_sign(method, path, &query_pairs);
+    let auth_signature = create_auth_signature(&to_sign, secret);
 
     let query_buffer = String::new();
     let mut query_serializer = Serializer::new(query_buffer);
 
-    for (k, v) in query_pairs {
-        query_serializer.append_pair(k, v);
+    for (k, v) in canonical_pairs(&query_pairs) {
+        query_serializer.append_pair(&k, v);
     }
     query_serializer.append_pair("auth_signature", auth_signature.as_str());
 
-    return query_serializer.finish();
+    query_serializer.finish()
 }
 
 #[test]
@@ -161,3 +157,60 @@ fn test_get_users_url() {
     let query = build_query("GET", "/apps/102015/channels/presence-session-d41a439c438a100756f5-4bf35003e819bb138249-nYJLy67qh52/users", "key", "secret", "1427053326".to_string(), None, None);
     assert_eq!(expected, query)
 }
+
+// The example from the REST API documentation, which the other official
+// libraries test against too.
+#[test]
+fn test_documented_signature() {
+    let expected = "auth_key=278d425bdf160c739803&auth_timestamp=1353088179&auth_version=1.0&body_md5=ec365a775a4cd0599faeb73354201b6f&auth_signature=da454824c97ba181a32ccc17a72625ba02771f50b50e1e7430e47a1f3f457e6c";
+    let payload =
+        "{\"name\":\"foo\",\"channels\":[\"project-3\"],\"data\":\"{\\\"some\\\":\\\"data\\\"}\"}";
+    let query = build_query(
+        "POST",
+        "/apps/3/events",
+        "278d425bdf160c739803",
+        "7ad3773142a6692b25b8",
+        "1353088179".to_string(),
+        Some(payload),
+        None,
+    );
+    assert_eq!(expected, query)
+}
+
+#[test]
+fn test_query_parameters_are_canonicalised() {
+    // The same request as test_get_channels_url, with the parameters out of
+    // order and a key in uppercase.
+    let expected = "auth_key=key&auth_timestamp=1427034994&auth_version=1.0&filter_by_prefix=presence-&info=user_count&auth_signature=0ba82990cff5311f09d88d8c9317d1ceb1b2e085c01deb65618f4eaea1624d89";
+    let query_parameters = Some(vec![
+        ("info".to_string(), "user_count".to_string()),
+        ("FILTER_BY_PREFIX".to_string(), "presence-".to_string()),
+    ]);
+    let query = build_query(
+        "GET",
+        "/apps/102015/channels",
+        "key",
+        "secret",
+        "1427034994".to_string(),
+        None,
+        query_parameters,
+    );
+    assert_eq!(expected, query)
+}
+
+#[test]
+fn test_string_to_sign() {
+    let to_sign = string_to_sign(
+        "get",
+        "/apps/3/channels",
+        &[
+            ("info", "user_count,subscription_count"),
+            ("auth_signature", "ignored"),
+            ("Auth_Key", "key"),
+        ],
+    );
+    assert_eq!(
+        to_sign,
+        "GET\n/apps/3/channels\nauth_key=key&info=user_count,subscription_count"
+    )
+}
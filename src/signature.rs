@@ -1,8 +1,9 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hex::{FromHex, ToHex};
 use hmac::{Hmac, Mac};
-use md5::{Md5, Digest};
+use md5::{Digest, Md5};
 use sha2::Sha256;
-use hex::{FromHex, ToHex};
-use std::collections::HashMap;
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -13,26 +14,45 @@ pub fn create_body_md5(body: &str) -> String {
     result.encode_hex()
 }
 
-pub fn create_channel_auth<'a>(
-    auth_map: &mut HashMap<&'a str, String>,
-    key: &str,
-    secret: &str,
-    to_sign: &str,
-) {
+pub fn create_channel_auth(key: &str, secret: &str, to_sign: &str) -> String {
     let auth_signature = create_auth_signature(to_sign, secret);
-    let auth_string = format!("{}:{}", key, auth_signature);
-    auth_map.insert("auth", auth_string);
+    format!("{}:{}", key, auth_signature)
+}
+
+/// The key a client uses to decrypt the events on an encrypted channel.
+pub fn create_shared_secret(channel_name: &str, master_key: &[u8]) -> String {
+    let mut sh = Sha256::new();
+    sh.update(channel_name.as_bytes());
+    sh.update(master_key);
+    STANDARD.encode(sh.finalize())
 }
 
 pub fn check_signature(signature: &str, secret: &str, body: &str) -> bool {
-    let mut hmac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    let decoded_signature = match Vec::from_hex(signature) {
+        Ok(decoded_signature) => decoded_signature,
+        Err(_) => return false,
+    };
+    let mut hmac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
     hmac.update(body.as_bytes());
-    let decoded_signature = Vec::from_hex(signature).unwrap();
     hmac.verify_slice(&decoded_signature).is_ok()
 }
 
-pub fn create_auth_signature<'a>(to_sign: &str, secret: &'a str) -> String {
-    let mut hmac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+/// Checks a hex-encoded HMAC-SHA256 signature of `to_sign`, in constant time.
+pub fn verify_auth_signature(to_sign: &str, secret: &str, signature: &str) -> bool {
+    let decoded_signature = match Vec::from_hex(signature) {
+        Ok(decoded_signature) => decoded_signature,
+        Err(_) => return false,
+    };
+    let mut hmac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    hmac.update(to_sign.as_bytes());
+    hmac.verify_slice(&decoded_signature).is_ok()
+}
+
+pub fn create_auth_signature(to_sign: &str, secret: &str) -> String {
+    let mut hmac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
     hmac.update(to_sign.as_bytes());
     let result = hmac.finalize();
     let code = result.into_bytes();
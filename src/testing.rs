@@ -0,0 +1,683 @@
+//! An in-process stand-in for the Pusher HTTP API, for integration tests. It is
+//! enabled by the `testing` feature.
+//!
+//! `MockServer` serves the REST endpoints on a local port, from a background
+//! thread, so it can be used with both `Pusher` and `blocking::Pusher`.
+//! Requests are checked with a `RequestVerifier` for the app's key and secret,
+//! and rejected with a 401 if they were not signed properly, as the real API
+//! would. Triggered events are recorded for later assertions, and responses
+//! can be made to fail or to be delayed.
+//!
+//! For unit tests of code written against the `PusherApi` trait,
+//! `FakePusher` does the same in memory, without a server.
+//!
+//! **Example:**
+//!
+//! ```
+//! # use pusher::testing::MockServer;
+//! # use pusher::PusherBuilder;
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let server = MockServer::start("id", "key", "secret");
+//! let pusher = PusherBuilder::from_url(&server.url()).finalize();
+//! pusher.trigger("test_channel", "my_event", "hello").await.unwrap();
+//!
+//! let events = server.events();
+//! assert_eq!(events[0].name, "my_event");
+//! assert_eq!(events[0].channels, vec!["test_channel"]);
+//! # }
+//! ```
+
+use hyper::body;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use super::api::{ApiFuture, PusherApi};
+use super::channel_query::*;
+use super::client::{Pusher, PusherBuilder};
+use super::clock::{Clock, SystemClock};
+use super::json_structures::*;
+use super::transport::{Transport, TransportFuture, TransportRequest, TransportResponse};
+use super::util::{ChannelType, SocketId};
+use super::verifier::RequestVerifier;
+
+/// An event received by a `MockServer`, from either the events or the
+/// batch_events endpoint.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct ReceivedEvent {
+    pub name: String,
+    /// The channels the event was triggered on; for batched events, the single
+    /// channel it was triggered on.
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// The payload, as the JSON string it was sent as.
+    pub data: String,
+    #[serde(default)]
+    pub socket_id: Option<String>,
+}
+
+// Batched events name a single channel rather than a list.
+#[derive(Deserialize)]
+struct BatchedEvent {
+    name: String,
+    channel: String,
+    data: String,
+    #[serde(default)]
+    socket_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Batch {
+    batch: Vec<BatchedEvent>,
+}
+
+/// A request received by a `MockServer`, whether or not it was accepted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReceivedRequest {
+    pub method: String,
+    /// The path, without the query string.
+    pub path: String,
+    /// The decoded query parameters, including the `auth_*` ones.
+    pub query: BTreeMap<String, String>,
+    pub body: String,
+}
+
+#[derive(Default)]
+struct State {
+    requests: Vec<ReceivedRequest>,
+    events: Vec<ReceivedEvent>,
+    terminated_users: Vec<String>,
+    channels: BTreeMap<String, Channel>,
+    users: HashMap<String, Vec<ChannelUser>>,
+    failures: VecDeque<(u16, String)>,
+    delay: Option<Duration>,
+}
+
+struct App {
+    app_id: String,
+    key: String,
+    secret: String,
+    verifier: RequestVerifier,
+    state: Mutex<State>,
+}
+
+/// A local HTTP server implementing the Pusher REST API for one app. It shuts
+/// down when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    app: Arc<App>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    /// Starts a server for the given app on an ephemeral port of 127.0.0.1.
+    pub fn start(app_id: &str, key: &str, secret: &str) -> MockServer {
+        MockServer::start_with_clock(app_id, key, secret, SystemClock)
+    }
+
+    /// Like `start`, but checks the timestamps of requests against `clock`,
+    /// for clients built with `PusherBuilder::clock`.
+    pub fn start_with_clock<C: Clock + 'static>(
+        app_id: &str,
+        key: &str,
+        secret: &str,
+        clock: C,
+    ) -> MockServer {
+        let mut app = App::new(app_id, key, secret);
+        app.verifier = app.verifier.clock(clock);
+        let app = Arc::new(app);
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        let (addr_tx, addr_rx) = mpsc::channel();
+
+        let server_app = app.clone();
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to start the mock server's runtime");
+            runtime.block_on(async move {
+                let make_svc = make_service_fn(move |_conn| {
+                    let app = server_app.clone();
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |req| handle(app.clone(), req)))
+                    }
+                });
+                let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+                addr_tx.send(server.local_addr()).unwrap();
+                let _ = server
+                    .with_graceful_shutdown(async {
+                        let _ = shutdown_rx.await;
+                    })
+                    .await;
+            });
+        });
+
+        MockServer {
+            addr: addr_rx.recv().expect("failed to start the mock server"),
+            app,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// The address the server listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// A Pusher URL for the server, to pass to `PusherBuilder::from_url`.
+    pub fn url(&self) -> String {
+        format!(
+            "http://{}:{}@{}/apps/{}",
+            self.app.key, self.app.secret, self.addr, self.app.app_id
+        )
+    }
+
+    /// The events triggered so far, in the order they were received.
+    pub fn events(&self) -> Vec<ReceivedEvent> {
+        self.state().events.clone()
+    }
+
+    /// All requests received so far, including rejected ones.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state().requests.clone()
+    }
+
+    /// The ids of the users whose connections were terminated so far.
+    pub fn terminated_users(&self) -> Vec<String> {
+        self.state().terminated_users.clone()
+    }
+
+    /// Sets the state of a channel, as returned by the channel and channels
+    /// endpoints.
+    pub fn set_channel(&self, channel_name: &str, channel: Channel) {
+        self.state()
+            .channels
+            .insert(channel_name.to_string(), channel);
+    }
+
+    /// Sets the users subscribed to a presence channel. The channel is marked
+    /// as occupied, with a matching user count.
+    pub fn set_users(&self, channel_name: &str, users: Vec<ChannelUser>) {
+        self.app.set_users(channel_name, users)
+    }
+
+    /// Makes the next signed request fail with the given status and body.
+    /// Calls queue up, one failure per request.
+    pub fn fail_next(&self, status: u16, body: &str) {
+        self.state().failures.push_back((status, body.to_string()));
+    }
+
+    /// Delays every response by `delay`, or stops delaying them if `None`.
+    pub fn set_delay(&self, delay: Option<Duration>) {
+        self.state().delay = delay;
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.app.state()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// An in-memory implementation of `PusherApi`, for unit tests of application
+/// code written against the trait.
+///
+/// It is a `Pusher` whose requests are answered in memory by the same logic as
+/// `MockServer`, so requests are validated and signed just as they would be for
+/// real. It records the events triggered, and the responses to channel queries
+/// can be scripted. Delays are not supported.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::testing::FakePusher;
+/// use pusher::PusherApi;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let pusher = FakePusher::new("id", "key", "secret");
+/// pusher.trigger("test_channel", "my_event", "hello").await.unwrap();
+/// assert_eq!(pusher.events_on("test_channel")[0].data, "\"hello\"");
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct FakePusher {
+    pusher: Pusher<InMemoryTransport>,
+    app: Arc<App>,
+}
+
+struct InMemoryTransport {
+    app: Arc<App>,
+}
+
+impl Transport for InMemoryTransport {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        let received = ReceivedRequest {
+            method: request.method.clone(),
+            path: request.url.path().to_string(),
+            query: request.url.query_pairs().into_owned().collect(),
+            body: request.body.clone().unwrap_or_default(),
+        };
+        let (status, body) = self
+            .app
+            .respond(received, request.url.query().unwrap_or(""));
+        Box::pin(async move {
+            Ok(TransportResponse {
+                status: status.as_u16(),
+                headers: Vec::new(),
+                body: body.into_bytes(),
+            })
+        })
+    }
+}
+
+impl FakePusher {
+    /// Creates a fake for the given app. Its credentials are used to sign
+    /// requests and channel authentication responses.
+    pub fn new(app_id: &str, key: &str, secret: &str) -> FakePusher {
+        let app = Arc::new(App::new(app_id, key, secret));
+        let transport = InMemoryTransport { app: app.clone() };
+        FakePusher {
+            pusher: PusherBuilder::new_with_client(transport, app_id, key, secret).finalize(),
+            app,
+        }
+    }
+
+    /// The events triggered so far, in the order they were triggered.
+    pub fn events(&self) -> Vec<ReceivedEvent> {
+        self.app.state().events.clone()
+    }
+
+    /// The events triggered so far on the given channel.
+    pub fn events_on(&self, channel_name: &str) -> Vec<ReceivedEvent> {
+        self.app
+            .state()
+            .events
+            .iter()
+            .filter(|event| event.channels.iter().any(|c| c == channel_name))
+            .cloned()
+            .collect()
+    }
+
+    /// All requests made so far.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.app.state().requests.clone()
+    }
+
+    /// See `MockServer::set_channel`.
+    pub fn set_channel(&self, channel_name: &str, channel: Channel) {
+        self.app
+            .state()
+            .channels
+            .insert(channel_name.to_string(), channel);
+    }
+
+    /// See `MockServer::set_users`.
+    pub fn set_users(&self, channel_name: &str, users: Vec<ChannelUser>) {
+        self.app.set_users(channel_name, users)
+    }
+
+    /// See `MockServer::fail_next`.
+    pub fn fail_next(&self, status: u16, body: &str) {
+        self.app
+            .state()
+            .failures
+            .push_back((status, body.to_string()));
+    }
+}
+
+impl PusherApi for FakePusher {
+    fn trigger<S: serde::Serialize>(
+        &self,
+        channel: &str,
+        event: &str,
+        payload: S,
+    ) -> ApiFuture<'_, TriggeredEvents> {
+        PusherApi::trigger(&self.pusher, channel, event, payload)
+    }
+
+    fn trigger_exclusive<S, I>(
+        &self,
+        channel: &str,
+        event: &str,
+        payload: S,
+        socket_id: I,
+    ) -> ApiFuture<'_, TriggeredEvents>
+    where
+        S: serde::Serialize,
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        PusherApi::trigger_exclusive(&self.pusher, channel, event, payload, socket_id)
+    }
+
+    fn trigger_multi<S: serde::Serialize>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+    ) -> ApiFuture<'_, TriggeredEvents> {
+        PusherApi::trigger_multi(&self.pusher, channels, event, payload)
+    }
+
+    fn trigger_multi_exclusive<S, I>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+        socket_id: I,
+    ) -> ApiFuture<'_, TriggeredEvents>
+    where
+        S: serde::Serialize,
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        PusherApi::trigger_multi_exclusive(&self.pusher, channels, event, payload, socket_id)
+    }
+
+    fn trigger_many_channels<S: serde::Serialize>(
+        &self,
+        channels: &[&str],
+        event: &str,
+        payload: S,
+        concurrency: usize,
+    ) -> ApiFuture<'_, TriggeredEvents> {
+        PusherApi::trigger_many_channels(&self.pusher, channels, event, payload, concurrency)
+    }
+
+    fn trigger_batch(&self, events: Vec<BatchEvent>) -> ApiFuture<'_, TriggeredBatch> {
+        PusherApi::trigger_batch(&self.pusher, events)
+    }
+
+    fn channels(&self) -> ApiFuture<'_, ChannelList> {
+        PusherApi::channels(&self.pusher)
+    }
+
+    fn channels_with_options(&self, params: QueryParameters) -> ApiFuture<'_, ChannelList> {
+        PusherApi::channels_with_options(&self.pusher, params)
+    }
+
+    fn channels_with_query(&self, query: ChannelsQuery) -> ApiFuture<'_, ChannelList> {
+        PusherApi::channels_with_query(&self.pusher, query)
+    }
+
+    fn channel(&self, channel_name: &str) -> ApiFuture<'_, Channel> {
+        PusherApi::channel(&self.pusher, channel_name)
+    }
+
+    fn channel_with_options(
+        &self,
+        channel_name: &str,
+        params: QueryParameters,
+    ) -> ApiFuture<'_, Channel> {
+        PusherApi::channel_with_options(&self.pusher, channel_name, params)
+    }
+
+    fn channel_with_query(
+        &self,
+        channel_name: &str,
+        query: ChannelQuery,
+    ) -> ApiFuture<'_, Channel> {
+        PusherApi::channel_with_query(&self.pusher, channel_name, query)
+    }
+
+    fn channel_users(&self, channel_name: &str) -> ApiFuture<'_, ChannelUserList> {
+        PusherApi::channel_users(&self.pusher, channel_name)
+    }
+
+    fn authenticate_private_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+    ) -> Result<String, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        self.pusher
+            .authenticate_private_channel(channel_name, socket_id)
+    }
+
+    fn authenticate_presence_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+        member: &Member,
+    ) -> Result<String, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        self.pusher
+            .authenticate_presence_channel(channel_name, socket_id, member)
+    }
+
+    fn authorize_private_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+    ) -> Result<ChannelAuthResponse, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        self.pusher
+            .authorize_private_channel(channel_name, socket_id)
+    }
+
+    fn authorize_presence_channel<I>(
+        &self,
+        channel_name: &str,
+        socket_id: I,
+        member: &Member,
+    ) -> Result<ChannelAuthResponse, &str>
+    where
+        I: TryInto<SocketId, Error = &'static str>,
+    {
+        self.pusher
+            .authorize_presence_channel(channel_name, socket_id, member)
+    }
+
+    fn webhook(&self, key: &str, signature: &str, body: &str) -> Result<Webhook, &str> {
+        self.pusher.webhook(key, signature, body)
+    }
+}
+
+async fn handle(app: Arc<App>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let raw_query = req.uri().query().unwrap_or("").to_string();
+    let query = url::form_urlencoded::parse(raw_query.as_bytes())
+        .into_owned()
+        .collect();
+    let body = match body::to_bytes(req.into_body()).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(_) => String::new(),
+    };
+
+    let request = ReceivedRequest {
+        method: method.to_string(),
+        path,
+        query,
+        body,
+    };
+    let delay = app.state.lock().unwrap().delay;
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let (status, body) = app.respond(request, &raw_query);
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    Ok(response)
+}
+
+impl App {
+    fn new(app_id: &str, key: &str, secret: &str) -> App {
+        App {
+            app_id: app_id.to_string(),
+            key: key.to_string(),
+            secret: secret.to_string(),
+            verifier: RequestVerifier::new(key, secret),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn set_users(&self, channel_name: &str, users: Vec<ChannelUser>) {
+        let mut state = self.state();
+        let channel = state.channels.entry(channel_name.to_string()).or_default();
+        channel.occupied = Some(!users.is_empty());
+        channel.user_count = Some(users.len() as u64);
+        state.users.insert(channel_name.to_string(), users);
+    }
+
+    // Records a request and works out the response to it. The raw query
+    // string is needed to check the signature.
+    fn respond(&self, request: ReceivedRequest, raw_query: &str) -> (StatusCode, String) {
+        self.state().requests.push(request.clone());
+        let verified =
+            self.verifier
+                .verify(&request.method, &request.path, raw_query, &request.body);
+        if let Err(err) = verified {
+            return (StatusCode::UNAUTHORIZED, err.to_string());
+        }
+        let failure = self.state().failures.pop_front();
+        match failure {
+            Some((status, body)) => (
+                StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                body,
+            ),
+            None => self.route(&request),
+        }
+    }
+
+    fn route(&self, request: &ReceivedRequest) -> (StatusCode, String) {
+        let segments: Vec<&str> = request.path.split('/').collect();
+        // Any segments before `apps` are a base path, which is ignored.
+        let apps = match segments.iter().position(|segment| *segment == "apps") {
+            Some(apps) => apps,
+            None => return not_found(),
+        };
+        if segments.get(apps + 1) != Some(&self.app_id.as_str()) {
+            return not_found();
+        }
+
+        let mut state = self.state();
+        match (request.method.as_str(), &segments[apps + 2..]) {
+            ("POST", ["events"]) => match serde_json::from_str::<ReceivedEvent>(&request.body) {
+                Ok(event) => {
+                    state.events.push(event);
+                    (StatusCode::OK, "{}".to_string())
+                }
+                Err(err) => bad_request(&err.to_string()),
+            },
+            ("POST", ["batch_events"]) => match serde_json::from_str::<Batch>(&request.body) {
+                Ok(batch) => {
+                    let responses = vec!["{}"; batch.batch.len()].join(",");
+                    state
+                        .events
+                        .extend(batch.batch.into_iter().map(|event| ReceivedEvent {
+                            name: event.name,
+                            channels: vec![event.channel],
+                            data: event.data,
+                            socket_id: event.socket_id,
+                        }));
+                    (StatusCode::OK, format!("{{\"batch\":[{}]}}", responses))
+                }
+                Err(err) => bad_request(&err.to_string()),
+            },
+            ("GET", ["channels"]) => {
+                let prefix = request
+                    .query
+                    .get("filter_by_prefix")
+                    .map(String::as_str)
+                    .unwrap_or("");
+                let info = info_attributes(request);
+                let channels: BTreeMap<&String, Channel> = state
+                    .channels
+                    .iter()
+                    .filter(|(name, channel)| {
+                        name.starts_with(prefix) && channel.occupied != Some(false)
+                    })
+                    .map(|(name, channel)| {
+                        let channel = Channel {
+                            user_count: channel.user_count.filter(|_| info.contains(&"user_count")),
+                            subscription_count: channel
+                                .subscription_count
+                                .filter(|_| info.contains(&"subscription_count")),
+                            ..Channel::default()
+                        };
+                        (name, channel)
+                    })
+                    .collect();
+                let body = serde_json::json!({ "channels": channels });
+                (StatusCode::OK, body.to_string())
+            }
+            ("GET", [channels, channel_name]) if *channels == "channels" => {
+                let info = info_attributes(request);
+                let channel = state
+                    .channels
+                    .get(*channel_name)
+                    .cloned()
+                    .unwrap_or_default();
+                let occupied = channel.occupied.unwrap_or(false);
+                let response = Channel {
+                    occupied: Some(occupied),
+                    user_count: channel.user_count.filter(|_| info.contains(&"user_count")),
+                    subscription_count: channel
+                        .subscription_count
+                        .filter(|_| info.contains(&"subscription_count")),
+                    cache: channel.cache.filter(|_| info.contains(&"cache")),
+                };
+                (StatusCode::OK, serde_json::to_string(&response).unwrap())
+            }
+            ("GET", [channels, channel_name, users])
+                if *channels == "channels" && *users == "users" =>
+            {
+                if !ChannelType::from_name(channel_name).is_presence() {
+                    return bad_request("Users can only be retrieved for presence channels");
+                }
+                let users = ChannelUserList {
+                    users: state.users.get(*channel_name).cloned().unwrap_or_default(),
+                };
+                (StatusCode::OK, serde_json::to_string(&users).unwrap())
+            }
+            ("POST", [users, user_id, terminate])
+                if *users == "users" && *terminate == "terminate_connections" =>
+            {
+                state.terminated_users.push(user_id.to_string());
+                (StatusCode::OK, "{}".to_string())
+            }
+            _ => not_found(),
+        }
+    }
+}
+
+fn info_attributes(request: &ReceivedRequest) -> Vec<&str> {
+    request
+        .query
+        .get("info")
+        .map(|info| info.split(',').collect())
+        .unwrap_or_default()
+}
+
+fn bad_request(message: &str) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, message.to_string())
+}
+
+fn not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Not found".to_string())
+}
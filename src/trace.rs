@@ -0,0 +1,71 @@
+use std::future::Future;
+use std::time::Instant;
+use tracing::{Instrument, Span};
+
+use super::transport::{TransportRequest, TransportResponse};
+
+// Spans only ever record the path of a request, never its query string, so
+// that the auth_signature is not logged. The secret is never in a request.
+
+/// The span around a trigger, which contains the span of its request.
+pub fn trigger_span(event: &str, channel_count: usize) -> Span {
+    tracing::info_span!("pusher.trigger", event, channels = channel_count)
+}
+
+/// The span around a batch trigger, which contains the span of its request.
+pub fn batch_span(event_count: usize) -> Span {
+    tracing::info_span!("pusher.trigger_batch", events = event_count)
+}
+
+/// The span around a single attempt at a request to the HTTP API, counting
+/// from 1. `status` and `latency_ms` are recorded once the response arrives.
+pub fn request_span(request: &TransportRequest, attempt: u32) -> Span {
+    tracing::info_span!(
+        "pusher.request",
+        method = %request.method,
+        path = request.url.path(),
+        payload_size = request.body.as_ref().map_or(0, String::len),
+        attempt,
+        status = tracing::field::Empty,
+        latency_ms = tracing::field::Empty,
+    )
+}
+
+pub async fn record<F>(span: Span, response: F) -> Result<TransportResponse, String>
+where
+    F: Future<Output = Result<TransportResponse, String>>,
+{
+    let start = Instant::now();
+    let response = response.instrument(span.clone()).await;
+    record_response(&span, &response, start);
+    response
+}
+
+#[cfg(feature = "blocking")]
+pub fn record_blocking<F>(span: Span, send: F) -> Result<TransportResponse, String>
+where
+    F: FnOnce() -> Result<TransportResponse, String>,
+{
+    let start = Instant::now();
+    let response = span.in_scope(send);
+    record_response(&span, &response, start);
+    response
+}
+
+fn record_response(span: &Span, response: &Result<TransportResponse, String>, start: Instant) {
+    if let Ok(response) = response {
+        span.record("status", response.status);
+    }
+    span.record("latency_ms", start.elapsed().as_millis() as u64);
+    let _entered = span.enter();
+    match response {
+        Ok(response) => {
+            if response.status == 200 {
+                tracing::debug!("request succeeded");
+            } else {
+                tracing::warn!(status = response.status, "request failed");
+            }
+        }
+        Err(err) => tracing::warn!(error = %err, "request failed"),
+    }
+}
@@ -0,0 +1,163 @@
+use hyper::body::{self, Buf};
+use hyper::client::connect::Connect;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Uri};
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use url::Url;
+
+#[cfg(feature = "hyper1")]
+mod hyper1;
+#[cfg(feature = "reqwest")]
+mod reqwest;
+#[cfg(feature = "tower")]
+mod tower;
+#[cfg(feature = "ureq")]
+mod ureq;
+
+#[cfg(feature = "tower")]
+pub use self::tower::{ServiceTransport, TransportService};
+
+/// A signed request to the HTTP API, ready to be sent by a `Transport`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransportRequest {
+    /// The HTTP method, e.g. `"GET"` or `"POST"`.
+    pub method: String,
+    /// The full URL, including the signed query string.
+    pub url: Url,
+    /// Headers to send with the request.
+    pub headers: Vec<(String, String)>,
+    /// The JSON body, if any.
+    pub body: Option<String>,
+}
+
+impl TransportRequest {
+    /// Creates a request with a JSON content type.
+    pub fn new(method: &str, url: Url, body: Option<String>) -> TransportRequest {
+        TransportRequest {
+            method: method.to_string(),
+            url,
+            headers: vec![(CONTENT_TYPE.to_string(), "application/json".to_string())],
+            body,
+        }
+    }
+}
+
+/// The response to a `TransportRequest`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransportResponse {
+    /// The HTTP status code.
+    pub status: u16,
+    /// The response headers, with lowercase names.
+    pub headers: Vec<(String, String)>,
+    /// The raw response body.
+    pub body: Vec<u8>,
+}
+
+impl TransportResponse {
+    /// Looks up a header by its (case-insensitive) name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// The future returned by `Transport::send`.
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<TransportResponse, String>> + Send + 'a>>;
+
+/// Sends signed requests to the HTTP API.
+///
+/// Signing, validation and response parsing happen in `Pusher`, so a transport
+/// only has to move bytes. It is implemented for hyper 0.14's `Client`, and,
+/// behind the `hyper1` and `reqwest` features, for hyper 1.x's legacy `Client`
+/// and `reqwest::Client`. An `Err` should only be returned if no response was
+/// received; error statuses are returned as responses.
+///
+/// Behind the `tower` feature, `TransportService` and `ServiceTransport` convert
+/// between transports and `tower::Service`s.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_>;
+}
+
+/// The synchronous counterpart of `Transport`. Behind the `ureq` feature, it is
+/// implemented for `ureq::Agent`.
+pub trait BlockingTransport: Send + Sync {
+    fn send(&self, request: TransportRequest) -> Result<TransportResponse, String>;
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        (**self).send(request)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        (**self).send(request)
+    }
+}
+
+impl<T: BlockingTransport + ?Sized> BlockingTransport for Arc<T> {
+    fn send(&self, request: TransportRequest) -> Result<TransportResponse, String> {
+        (**self).send(request)
+    }
+}
+
+impl<T: BlockingTransport + ?Sized> BlockingTransport for Box<T> {
+    fn send(&self, request: TransportRequest) -> Result<TransportResponse, String> {
+        (**self).send(request)
+    }
+}
+
+impl<C> Transport for Client<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let request_uri: Uri = FromStr::from_str(request.url.as_str())
+                .map_err(|err| format!("Error: invalid URL. {}", err))?;
+            let mut request_builder = hyper::Request::builder()
+                .method(request.method.as_str())
+                .uri(request_uri);
+            for (name, value) in &request.headers {
+                request_builder = request_builder.header(name.as_str(), value.as_str());
+            }
+            let hyper_request = match request.body {
+                Some(body) => request_builder.body(Body::from(body)),
+                None => request_builder.body(Body::empty()),
+            }
+            .map_err(|err| format!("Error: {}", err))?;
+
+            let response = self
+                .request(hyper_request)
+                .await
+                .map_err(|err| format!("Error: {}", err))?;
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect();
+            let mut body = body::aggregate(response)
+                .await
+                .map_err(|err| format!("Error: {}", err))?;
+            let body = body.copy_to_bytes(body.remaining()).to_vec();
+            Ok(TransportResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
@@ -0,0 +1,54 @@
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper_util::client::legacy::connect::Connect;
+use hyper_util::client::legacy::Client;
+
+use super::{Transport, TransportFuture, TransportRequest, TransportResponse};
+
+impl<C> Transport for Client<C, Full<Bytes>>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let mut request_builder = hyper1::Request::builder()
+                .method(request.method.as_str())
+                .uri(request.url.as_str());
+            for (name, value) in &request.headers {
+                request_builder = request_builder.header(name.as_str(), value.as_str());
+            }
+            let body = Full::new(Bytes::from(request.body.unwrap_or_default()));
+            let hyper_request = request_builder
+                .body(body)
+                .map_err(|err| format!("Error: {}", err))?;
+
+            let response = self
+                .request(hyper_request)
+                .await
+                .map_err(|err| format!("Error: {}", err))?;
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect();
+            let body = response
+                .into_body()
+                .collect()
+                .await
+                .map_err(|err| format!("Error: {}", err))?
+                .to_bytes()
+                .to_vec();
+            Ok(TransportResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
@@ -0,0 +1,46 @@
+use reqwest::{Client, Method};
+
+use super::{Transport, TransportFuture, TransportRequest, TransportResponse};
+
+impl Transport for Client {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let method = Method::from_bytes(request.method.as_bytes())
+                .map_err(|err| format!("Error: {}", err))?;
+            let mut request_builder = self.request(method, request.url);
+            for (name, value) in request.headers {
+                request_builder = request_builder.header(name, value);
+            }
+            if let Some(body) = request.body {
+                request_builder = request_builder.body(body);
+            }
+
+            // reqwest's errors include the URL, and with it the auth_signature.
+            let response = request_builder
+                .send()
+                .await
+                .map_err(|err| format!("Error: {}", err.without_url()))?;
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect();
+            let body = response
+                .bytes()
+                .await
+                .map_err(|err| format!("Error: {}", err.without_url()))?
+                .to_vec();
+            Ok(TransportResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Service, ServiceExt};
+
+use super::{Transport, TransportFuture, TransportRequest, TransportResponse};
+
+/// A `tower::Service` that sends requests with a `Transport`. It is the bottom
+/// of a stack of `tower::Layer`s, which can then be given to `Pusher` as a
+/// transport with `ServiceTransport`.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::{PusherBuilder, ServiceTransport, TransportRequest, TransportService};
+/// use tower::ServiceBuilder;
+///
+/// let service = ServiceBuilder::new()
+///     .map_request(|mut request: TransportRequest| {
+///         request.headers.push(("x-tenant-id".to_string(), "acme".to_string()));
+///         request
+///     })
+///     .service(TransportService::new(hyper::Client::new()));
+/// let pusher = PusherBuilder::new_with_client(ServiceTransport::new(service), "id", "key", "secret")
+///     .finalize();
+/// ```
+pub struct TransportService<T> {
+    transport: Arc<T>,
+}
+
+impl<T> TransportService<T> {
+    pub fn new(transport: T) -> TransportService<T> {
+        TransportService {
+            transport: Arc::new(transport),
+        }
+    }
+}
+
+impl<T> Clone for TransportService<T> {
+    fn clone(&self) -> TransportService<T> {
+        TransportService {
+            transport: Arc::clone(&self.transport),
+        }
+    }
+}
+
+impl<T> fmt::Debug for TransportService<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransportService").finish_non_exhaustive()
+    }
+}
+
+impl<T: Transport + 'static> Service<TransportRequest> for TransportService<T> {
+    type Response = TransportResponse;
+    type Error = String;
+    type Future = TransportFuture<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), String>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: TransportRequest) -> Self::Future {
+        let transport = Arc::clone(&self.transport);
+        Box::pin(async move { transport.send(request).await })
+    }
+}
+
+/// A `Transport` that sends requests through a `tower::Service`, such as a
+/// `TransportService` wrapped in layers for timeouts, retries or rate limiting.
+/// The service is cloned for each request, as is usual for tower services.
+#[derive(Clone, Debug)]
+pub struct ServiceTransport<S> {
+    service: S,
+}
+
+impl<S> ServiceTransport<S> {
+    pub fn new(service: S) -> ServiceTransport<S> {
+        ServiceTransport { service }
+    }
+}
+
+impl<S> Transport for ServiceTransport<S>
+where
+    S: Service<TransportRequest, Response = TransportResponse> + Clone + Send + Sync + 'static,
+    S::Future: Send,
+    S::Error: fmt::Display,
+{
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        let service = self.service.clone();
+        Box::pin(async move {
+            service.oneshot(request).await.map_err(|err| {
+                // Errors from the transport itself are already prefixed, but
+                // those from layers, such as timeouts, are not.
+                let message = err.to_string();
+                if message.starts_with("Error:") {
+                    message
+                } else {
+                    format!("Error: {}", message)
+                }
+            })
+        })
+    }
+}
@@ -0,0 +1,58 @@
+use std::io::Read;
+use ureq::{Agent, Response};
+
+use super::{BlockingTransport, TransportRequest, TransportResponse};
+
+impl BlockingTransport for Agent {
+    fn send(&self, request: TransportRequest) -> Result<TransportResponse, String> {
+        let mut ureq_request = self.request(&request.method, request.url.as_str());
+        for (name, value) in &request.headers {
+            ureq_request = ureq_request.set(name, value);
+        }
+        let result = match request.body {
+            Some(ref body) => ureq_request.send_string(body),
+            None => ureq_request.call(),
+        };
+        let response = match result {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(ureq::Error::Transport(err)) => return Err(transport_error(&err)),
+        };
+        into_transport_response(response)
+    }
+}
+
+// ureq's own message starts with the URL, which contains the auth_signature,
+// so it is rebuilt without it.
+fn transport_error(err: &ureq::Transport) -> String {
+    let mut message = format!("Error: {}", err.kind());
+    if let Some(detail) = err.message() {
+        message = format!("{}: {}", message, detail);
+    }
+    if let Some(source) = std::error::Error::source(err) {
+        message = format!("{}: {}", message, source);
+    }
+    message
+}
+
+fn into_transport_response(response: Response) -> Result<TransportResponse, String> {
+    let status = response.status();
+    let headers = response
+        .headers_names()
+        .into_iter()
+        .filter_map(|name| {
+            let value = response.header(&name)?.to_string();
+            Some((name, value))
+        })
+        .collect();
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut body)
+        .map_err(|err| format!("Error: {}", err))?;
+    Ok(TransportResponse {
+        status,
+        headers,
+        body,
+    })
+}
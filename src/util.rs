@@ -1,11 +1,275 @@
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::OnceLock;
 
-pub fn validate_channels(channels: &Vec<String>) -> Result<bool, String> {
-    if channels.len() > 10 {
-        return Err("Cannot trigger on more than 10 channels".to_string());
+use super::json_structures::BatchEvent;
+
+/// The kind of a channel, as determined by its name's prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelType {
+    /// A channel without a prefix, which anyone can subscribe to.
+    Public,
+    /// A `private-` channel.
+    Private,
+    /// A `presence-` channel.
+    Presence,
+    /// A `private-encrypted-` channel.
+    PrivateEncrypted,
+    /// A `cache-` channel.
+    Cache,
+    /// A `private-cache-` channel.
+    PrivateCache,
+    /// A `presence-cache-` channel.
+    PresenceCache,
+    /// A `private-encrypted-cache-` channel.
+    PrivateEncryptedCache,
+}
+
+// Longer prefixes come first, so that e.g. `private-cache-` wins over `private-`.
+const CHANNEL_PREFIXES: [(&str, ChannelType); 7] = [
+    (
+        "private-encrypted-cache-",
+        ChannelType::PrivateEncryptedCache,
+    ),
+    ("private-encrypted-", ChannelType::PrivateEncrypted),
+    ("private-cache-", ChannelType::PrivateCache),
+    ("presence-cache-", ChannelType::PresenceCache),
+    ("private-", ChannelType::Private),
+    ("presence-", ChannelType::Presence),
+    ("cache-", ChannelType::Cache),
+];
+
+impl ChannelType {
+    /// Classifies a channel by its name.
+    pub fn from_name(channel_name: &str) -> ChannelType {
+        for &(prefix, channel_type) in CHANNEL_PREFIXES.iter() {
+            if channel_name.starts_with(prefix) {
+                return channel_type;
+            }
+        }
+        ChannelType::Public
+    }
+
+    /// The name prefix identifying this kind of channel.
+    pub fn prefix(&self) -> &'static str {
+        for &(prefix, channel_type) in CHANNEL_PREFIXES.iter() {
+            if channel_type == *self {
+                return prefix;
+            }
+        }
+        ""
+    }
+
+    /// Does subscribing to the channel require authentication?
+    pub fn is_private(&self) -> bool {
+        *self != ChannelType::Public && *self != ChannelType::Cache
+    }
+
+    /// Does the channel track the users subscribed to it?
+    pub fn is_presence(&self) -> bool {
+        *self == ChannelType::Presence || *self == ChannelType::PresenceCache
+    }
+
+    /// Is the data of the channel's events end-to-end encrypted?
+    pub fn is_encrypted(&self) -> bool {
+        *self == ChannelType::PrivateEncrypted || *self == ChannelType::PrivateEncryptedCache
+    }
+
+    /// Does the channel cache the last event triggered on it?
+    pub fn is_cache(&self) -> bool {
+        matches!(
+            *self,
+            ChannelType::Cache
+                | ChannelType::PrivateCache
+                | ChannelType::PresenceCache
+                | ChannelType::PrivateEncryptedCache
+        )
+    }
+}
+
+// Events with these prefixes belong to the Pusher protocol itself.
+const RESERVED_EVENT_PREFIXES: [&str; 2] = ["pusher:", "pusher_internal:"];
+
+const CLIENT_EVENT_PREFIX: &str = "client-";
+
+/// The name of an event, checked against the HTTP API's rules: it is not
+/// empty, is at most 200 characters long, and does not use the `pusher:` or
+/// `pusher_internal:` prefixes reserved for the protocol. Every trigger
+/// validates its event name this way; `EventName` lets you do so up front.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::EventName;
+/// assert!(EventName::new("my_event").is_ok());
+/// assert!(EventName::new("pusher:subscribe").is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EventName(String);
+
+impl EventName {
+    pub fn new(name: &str) -> Result<EventName, String> {
+        if name.is_empty() {
+            return Err("Event name must not be empty".to_string());
+        }
+        if name.len() > 200 {
+            return Err("Event name is limited to 200 chars".to_string());
+        }
+        for prefix in RESERVED_EVENT_PREFIXES.iter() {
+            if name.starts_with(prefix) {
+                return Err(format!(
+                    "Event name {} uses the reserved prefix {}",
+                    name, prefix
+                ));
+            }
+        }
+        if name == CLIENT_EVENT_PREFIX {
+            return Err(format!(
+                "Event name {} is missing a name after its prefix",
+                name
+            ));
+        }
+        Ok(EventName(name.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Is this a client event, named with the `client-` prefix?
+    pub fn is_client_event(&self) -> bool {
+        self.0.starts_with(CLIENT_EVENT_PREFIX)
     }
 
-    let channel_regex = Regex::new(r"^[-a-zA-Z0-9_=@,.;]+$").unwrap(); // how to make this global?
+    /// Checks that the event can be triggered on the given channels: client
+    /// events are only allowed on private and presence channels that are not
+    /// end-to-end encrypted, as they are for clients.
+    pub fn check_channels(&self, channels: &[String]) -> Result<(), String> {
+        if !self.is_client_event() {
+            return Ok(());
+        }
+        for channel in channels {
+            let channel_type = ChannelType::from_name(channel);
+            if !channel_type.is_private() || channel_type.is_encrypted() {
+                return Err(format!(
+                    "Client event {} can only be triggered on private or presence channels, not {}",
+                    self.0, channel
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AsRef<str> for EventName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for EventName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The id of a client's connection, as given to it by Pusher, e.g.
+/// `"1234.5678"`. Methods that authenticate a client for a channel, or exclude
+/// it from an event, take either a `&str`, which they check, or a `&SocketId`
+/// checked beforehand, so that malformed ids are caught before they reach the
+/// API.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::SocketId;
+/// assert!(SocketId::new("1234.5678").is_ok());
+/// assert_eq!(SocketId::new("12345678"), Err("Invalid socket_id"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SocketId(String);
+
+impl SocketId {
+    pub fn new(socket_id: &str) -> Result<SocketId, &'static str> {
+        static PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = PATTERN.get_or_init(|| Regex::new(r"\A\d+\.\d+\z").unwrap());
+        if !pattern.is_match(socket_id) {
+            return Err("Invalid socket_id");
+        }
+        Ok(SocketId(socket_id.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for SocketId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SocketId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<&str> for SocketId {
+    type Error = &'static str;
+
+    fn try_from(socket_id: &str) -> Result<SocketId, &'static str> {
+        SocketId::new(socket_id)
+    }
+}
+
+impl TryFrom<&String> for SocketId {
+    type Error = &'static str;
+
+    fn try_from(socket_id: &String) -> Result<SocketId, &'static str> {
+        SocketId::new(socket_id)
+    }
+}
+
+impl TryFrom<String> for SocketId {
+    type Error = &'static str;
+
+    fn try_from(socket_id: String) -> Result<SocketId, &'static str> {
+        SocketId::new(&socket_id)
+    }
+}
+
+impl TryFrom<&SocketId> for SocketId {
+    type Error = &'static str;
+
+    fn try_from(socket_id: &SocketId) -> Result<SocketId, &'static str> {
+        Ok(socket_id.clone())
+    }
+}
+
+impl From<SocketId> for String {
+    fn from(socket_id: SocketId) -> String {
+        socket_id.0
+    }
+}
+
+/// The most channels the HTTP API accepts in a single trigger.
+pub const MAX_CHANNELS_PER_TRIGGER: usize = 100;
+
+pub fn validate_channels(channels: &Vec<String>, max_channels: usize) -> Result<bool, String> {
+    if channels.len() > max_channels {
+        return Err(format!(
+            "Cannot trigger on more than {} channels",
+            max_channels
+        ));
+    }
+
+    static CHANNEL_PATTERN: OnceLock<Regex> = OnceLock::new();
+    let channel_regex =
+        CHANNEL_PATTERN.get_or_init(|| Regex::new(r"^[-a-zA-Z0-9_=@,.;]+$").unwrap());
 
     for channel in channels {
         if channel.len() > 200 {
@@ -14,6 +278,116 @@ pub fn validate_channels(channels: &Vec<String>) -> Result<bool, String> {
         if !channel_regex.is_match(channel) {
             return Err("Channels must be formatted as such: ^[-a-zA-Z0-9_=@,.;]+$".to_string());
         }
+        if channel.as_str() == ChannelType::from_name(channel).prefix() {
+            return Err(format!(
+                "Channel name {} is missing a name after its prefix",
+                channel
+            ));
+        }
     }
     Ok(true)
 }
+
+pub fn validate_batch_event(event: &BatchEvent) -> Result<(), String> {
+    let name = EventName::new(&event.name)?;
+    let channels = vec![event.channel.clone()];
+    validate_channels(&channels, 1)?;
+    name.check_channels(&channels)?;
+    if event.data.len() > 10240 {
+        return Err("Data must be smaller than 10kb".to_string());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_channel_type_from_name() {
+    assert_eq!(ChannelType::from_name("foo"), ChannelType::Public);
+    assert_eq!(ChannelType::from_name("cache-foo"), ChannelType::Cache);
+    assert_eq!(
+        ChannelType::from_name("private-cache-foo"),
+        ChannelType::PrivateCache
+    );
+    assert_eq!(
+        ChannelType::from_name("presence-cache-foo"),
+        ChannelType::PresenceCache
+    );
+    assert_eq!(
+        ChannelType::from_name("private-encrypted-cache-foo"),
+        ChannelType::PrivateEncryptedCache
+    );
+    assert_eq!(
+        ChannelType::from_name("private-encrypted-foo"),
+        ChannelType::PrivateEncrypted
+    );
+    assert!(ChannelType::from_name("presence-cache-foo").is_presence());
+    assert!(!ChannelType::from_name("presence-foo").is_cache());
+}
+
+#[test]
+fn test_event_name_validation() {
+    assert_eq!(EventName::new("my_event").unwrap().as_str(), "my_event");
+    assert_eq!(
+        EventName::new("").unwrap_err(),
+        "Event name must not be empty"
+    );
+    assert_eq!(
+        EventName::new(&"a".repeat(201)).unwrap_err(),
+        "Event name is limited to 200 chars"
+    );
+    assert_eq!(
+        EventName::new("pusher:subscribe").unwrap_err(),
+        "Event name pusher:subscribe uses the reserved prefix pusher:"
+    );
+    assert!(EventName::new("pusher_internal:member_added").is_err());
+    assert!(EventName::new("pusher-event").is_ok());
+    assert!(EventName::new("client-").is_err());
+
+    let event = EventName::new("client-typing").unwrap();
+    assert!(event.is_client_event());
+    let channels = vec!["private-chat".to_string(), "presence-room".to_string()];
+    assert!(event.check_channels(&channels).is_ok());
+    for channel in ["chat", "cache-chat", "private-encrypted-chat"] {
+        assert_eq!(
+            event.check_channels(&[channel.to_string()]).unwrap_err(),
+            format!(
+                "Client event client-typing can only be triggered on private or presence channels, not {}",
+                channel
+            )
+        );
+    }
+    assert!(EventName::new("typing")
+        .unwrap()
+        .check_channels(&["chat".to_string()])
+        .is_ok());
+}
+
+#[test]
+fn test_socket_id_validation() {
+    assert_eq!(SocketId::new("1234.5678").unwrap().as_str(), "1234.5678");
+    for socket_id in [
+        "12345678",
+        "1234.",
+        ".5678",
+        "1234.5678\n",
+        "a1234.5678",
+        "",
+    ] {
+        assert_eq!(
+            SocketId::new(socket_id),
+            Err("Invalid socket_id"),
+            "{:?}",
+            socket_id
+        );
+    }
+}
+
+#[test]
+fn test_validate_cache_channels() {
+    let channels = vec!["cache-foo".to_string(), "private-cache-foo".to_string()];
+    assert!(validate_channels(&channels, MAX_CHANNELS_PER_TRIGGER).is_ok());
+    let channels = vec!["private-cache-".to_string()];
+    assert_eq!(
+        validate_channels(&channels, MAX_CHANNELS_PER_TRIGGER).unwrap_err(),
+        "Channel name private-cache- is missing a name after its prefix"
+    )
+}
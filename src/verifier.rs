@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use url::form_urlencoded;
+
+use super::clock::{unix_timestamp, Clock, SystemClock};
+use super::request_url::string_to_sign;
+use super::signature::*;
+
+/// Verifies that requests to the HTTP API were signed with an app's
+/// credentials, as a Pusher server would. This is the inverse of the signing
+/// done by `Pusher`, for proxies or gateways that sit in front of the API.
+///
+/// A request is accepted if its `auth_key` matches, its `auth_signature` is the
+/// HMAC of the canonical request string, its `body_md5` matches the body, and
+/// its `auth_timestamp` is within the timestamp window of the current time.
+///
+/// **Example:**
+///
+/// ```
+/// # use pusher::RequestVerifier;
+/// let verifier = RequestVerifier::new("key", "secret");
+/// let result = verifier.verify(
+///     "GET",
+///     "/apps/3/channels",
+///     "auth_key=key&auth_timestamp=1353088179&auth_version=1.0&auth_signature=nope",
+///     "",
+/// );
+/// assert!(result.is_err());
+/// ```
+#[derive(Clone)]
+pub struct RequestVerifier {
+    key: String,
+    secret: String,
+    timestamp_window: u64,
+    clock: Arc<dyn Clock>,
+}
+
+impl RequestVerifier {
+    /// Creates a verifier for the app with the given key and secret. The
+    /// timestamp window defaults to 600 seconds, as used by Pusher.
+    pub fn new(key: &str, secret: &str) -> RequestVerifier {
+        RequestVerifier {
+            key: key.to_string(),
+            secret: secret.to_string(),
+            timestamp_window: 600,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Sets how many seconds a request's `auth_timestamp` may differ from the
+    /// current time by, in either direction.
+    pub fn timestamp_window(mut self, seconds: u64) -> RequestVerifier {
+        self.timestamp_window = seconds;
+        self
+    }
+
+    /// Sets the clock the timestamp window is checked against, which defaults
+    /// to `SystemClock`.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> RequestVerifier {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Verifies a request, given its method, its path as received by the
+    /// server, its raw (URL-encoded) query string, and its body, which is empty
+    /// if there is none.
+    pub fn verify(
+        &self,
+        method: &str,
+        path: &str,
+        query: &str,
+        body: &str,
+    ) -> Result<(), &'static str> {
+        let now = unix_timestamp(&*self.clock);
+        self.verify_at(method, path, query, body, now)
+    }
+
+    fn verify_at(
+        &self,
+        method: &str,
+        path: &str,
+        query: &str,
+        body: &str,
+        now: u64,
+    ) -> Result<(), &'static str> {
+        let mut params = BTreeMap::new();
+        for (k, v) in form_urlencoded::parse(query.as_bytes()) {
+            if params.insert(k.to_lowercase(), v.into_owned()).is_some() {
+                return Err("Duplicate query parameter");
+            }
+        }
+
+        if params.get("auth_key") != Some(&self.key) {
+            return Err("Invalid auth_key");
+        }
+        if params.get("auth_version").map(String::as_str) != Some("1.0") {
+            return Err("Invalid auth_version");
+        }
+
+        let timestamp: u64 = match params.get("auth_timestamp").map(|t| t.parse()) {
+            Some(Ok(timestamp)) => timestamp,
+            _ => return Err("Invalid auth_timestamp"),
+        };
+        if now.abs_diff(timestamp) > self.timestamp_window {
+            return Err("Timestamp expired");
+        }
+
+        match params.get("body_md5") {
+            Some(body_md5) if *body_md5 != create_body_md5(body) => {
+                return Err("Invalid body_md5");
+            }
+            None if !body.is_empty() => return Err("Missing body_md5"),
+            _ => {}
+        }
+
+        let signature = match params.get("auth_signature") {
+            Some(signature) => signature,
+            None => return Err("Missing auth_signature"),
+        };
+        let query_pairs: Vec<(&str, &str)> = params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        let to_sign = string_to_sign(method, path, &query_pairs);
+        if !verify_auth_signature(&to_sign, &self.secret, signature) {
+            return Err("Invalid signature");
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_verify_trigger_request() {
+    let verifier = RequestVerifier::new("key", "secret");
+    let query = "auth_key=key&auth_timestamp=1353088179&auth_version=1.0&body_md5=ec365a775a4cd0599faeb73354201b6f&auth_signature=3695357e49aa04ae6f3cd76039dcefd82da079d0564bac566033d48ebae75459";
+    let body =
+        "{\"name\":\"foo\",\"channels\":[\"project-3\"],\"data\":\"{\\\"some\\\":\\\"data\\\"}\"}";
+    let result = verifier.verify_at("POST", "/apps/3/events", query, body, 1353088179);
+    assert_eq!(result, Ok(()));
+
+    let result = verifier.verify_at("POST", "/apps/3/events", query, "{}", 1353088179);
+    assert_eq!(result, Err("Invalid body_md5"));
+    let result = verifier.verify_at("POST", "/apps/4/events", query, body, 1353088179);
+    assert_eq!(result, Err("Invalid signature"));
+}
+
+#[test]
+fn test_verify_query_parameters() {
+    let verifier = RequestVerifier::new("key", "secret");
+    // The signature covers the parameters in sorted order, whatever order they
+    // are sent in.
+    let query = "info=user_count&auth_key=key&auth_timestamp=1427034994&auth_version=1.0&filter_by_prefix=presence-&auth_signature=0ba82990cff5311f09d88d8c9317d1ceb1b2e085c01deb65618f4eaea1624d89";
+    let result = verifier.verify_at("GET", "/apps/102015/channels", query, "", 1427034994);
+    assert_eq!(result, Ok(()));
+
+    let tampered = query.replace("presence-", "private-");
+    let result = verifier.verify_at("GET", "/apps/102015/channels", &tampered, "", 1427034994);
+    assert_eq!(result, Err("Invalid signature"));
+}
+
+#[test]
+fn test_verify_timestamp_window() {
+    let verifier = RequestVerifier::new("key", "secret");
+    let query = "auth_key=key&auth_timestamp=1427036787&auth_version=1.0&auth_signature=805473a9346a00c6ddca6059286f7f6b4e4c45dea1ead355f115decba06bfa4d";
+    let path = "/apps/102015/channels";
+    assert_eq!(
+        verifier.verify_at("GET", path, query, "", 1427036787 + 600),
+        Ok(())
+    );
+    assert_eq!(
+        verifier.verify_at("GET", path, query, "", 1427036787 - 600),
+        Ok(())
+    );
+    assert_eq!(
+        verifier.verify_at("GET", path, query, "", 1427036787 + 601),
+        Err("Timestamp expired")
+    );
+
+    let verifier = verifier.timestamp_window(30);
+    assert_eq!(
+        verifier.verify_at("GET", path, query, "", 1427036787 + 31),
+        Err("Timestamp expired")
+    );
+
+    let clock = || std::time::UNIX_EPOCH + std::time::Duration::from_secs(1427036787 + 30);
+    let verifier = verifier.clock(clock);
+    assert_eq!(verifier.verify("GET", path, query, ""), Ok(()));
+}
+
+#[test]
+fn test_verify_malformed_requests() {
+    let verifier = RequestVerifier::new("key", "secret");
+    let path = "/apps/102015/channels";
+    let now = 1427036787;
+    let query = "auth_key=key&auth_timestamp=1427036787&auth_version=1.0";
+    assert_eq!(
+        verifier.verify_at("GET", path, query, "", now),
+        Err("Missing auth_signature")
+    );
+    assert_eq!(
+        verifier.verify_at("POST", path, query, "{}", now),
+        Err("Missing body_md5")
+    );
+    let query = "auth_key=key&auth_timestamp=1427036787&auth_version=1.0&auth_signature=zz";
+    assert_eq!(
+        verifier.verify_at("GET", path, query, "", now),
+        Err("Invalid signature")
+    );
+    let query = "auth_key=other&auth_timestamp=1427036787&auth_version=1.0";
+    assert_eq!(
+        verifier.verify_at("GET", path, query, "", now),
+        Err("Invalid auth_key")
+    );
+    let query = "auth_key=key&auth_timestamp=soon&auth_version=1.0";
+    assert_eq!(
+        verifier.verify_at("GET", path, query, "", now),
+        Err("Invalid auth_timestamp")
+    );
+    let query = "auth_key=key&auth_key=key&auth_timestamp=1427036787&auth_version=1.0";
+    assert_eq!(
+        verifier.verify_at("GET", path, query, "", now),
+        Err("Duplicate query parameter")
+    );
+}
@@ -0,0 +1,98 @@
+extern crate pusher;
+extern crate serde;
+extern crate serde_json;
+
+use pusher::{CacheMiss, Channel, ChannelList, ChannelUserList, TriggeredEvents, Webhook};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+fn parse_fixture<T>(fixture: &str) -> T
+where
+    T: DeserializeOwned + Serialize + PartialEq + Debug,
+{
+    let parsed: T = serde_json::from_str(fixture).unwrap();
+    let reserialized = serde_json::to_string(&parsed).unwrap();
+    let reparsed: T = serde_json::from_str(&reserialized).unwrap();
+    assert_eq!(parsed, reparsed);
+    parsed
+}
+
+#[test]
+fn test_triggered_events_fixture() {
+    let events: TriggeredEvents = parse_fixture(include_str!("fixtures/triggered_events.json"));
+    let event_ids = events.event_ids.unwrap();
+    assert_eq!(event_ids["my-channel"], "eudhq1809scss2");
+    assert!(events.channels.is_none())
+}
+
+#[test]
+fn test_triggered_events_with_info_fixture() {
+    let events: TriggeredEvents =
+        parse_fixture(include_str!("fixtures/triggered_events_with_info.json"));
+    let channels = events.channels.unwrap();
+    assert_eq!(channels["presence-foobar"].user_count, Some(42));
+    assert_eq!(channels["presence-foobar"].subscription_count, Some(51));
+    assert_eq!(channels["cache-foobar"].user_count, None);
+    assert!(events.event_ids.is_none())
+}
+
+#[test]
+fn test_channel_list_fixture() {
+    let list: ChannelList = parse_fixture(include_str!("fixtures/channel_list.json"));
+    assert_eq!(list.channels.len(), 2);
+    assert_eq!(list.channels["presence-another"].user_count, Some(123));
+    assert_eq!(list.channels["presence-another"].occupied, None)
+}
+
+#[test]
+fn test_channel_fixture() {
+    let channel: Channel = parse_fixture(include_str!("fixtures/channel.json"));
+    assert_eq!(
+        channel,
+        Channel {
+            occupied: Some(true),
+            user_count: Some(42),
+            subscription_count: Some(42),
+            cache: None,
+        }
+    )
+}
+
+#[test]
+fn test_cache_channel_fixture() {
+    let channel: Channel = parse_fixture(include_str!("fixtures/cache_channel.json"));
+    let cache = channel.cache.unwrap();
+    assert_eq!(cache.data, "{\"some\":\"data\"}");
+    assert_eq!(cache.ttl, 1800);
+    assert_eq!(channel.subscription_count, Some(3))
+}
+
+#[test]
+fn test_channel_with_unknown_fields_fixture() {
+    let channel: Channel = parse_fixture(include_str!("fixtures/channel_with_unknown_fields.json"));
+    assert_eq!(channel.occupied, Some(false));
+    assert_eq!(channel.user_count, None)
+}
+
+#[test]
+fn test_channel_users_fixture() {
+    let list: ChannelUserList = parse_fixture(include_str!("fixtures/channel_users.json"));
+    let ids: Vec<&str> = list.users.iter().map(|user| user.id.as_str()).collect();
+    assert_eq!(ids, vec!["1", "2"])
+}
+
+#[test]
+fn test_webhook_fixture() {
+    let webhook: Webhook = parse_fixture(include_str!("fixtures/webhook.json"));
+    assert_eq!(webhook.time_ms, 1327078148132);
+    assert_eq!(webhook.events.len(), 5);
+    assert_eq!(webhook.events[1]["user_id"], "42");
+    assert_eq!(webhook.events[3]["subscription_count"], 2);
+    assert_eq!(
+        webhook.cache_misses(),
+        vec![CacheMiss {
+            channel: "cache-foobar".to_string()
+        }]
+    )
+}
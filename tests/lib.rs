@@ -1,3 +1,4 @@
+extern crate futures_util;
 extern crate hyper;
 extern crate log;
 extern crate pusher;
@@ -6,8 +7,9 @@ extern crate tokio;
 #[macro_use]
 extern crate yup_hyper_mock;
 
+use futures_util::StreamExt;
 use hyper::Client;
-use pusher::PusherBuilder;
+use pusher::{ChannelInfo, ChannelQuery, PusherBuilder};
 
 mock_connector!(BadRequest {
     "http://127.0.0.1" =>       "HTTP/1.1 400 Bad Request\r\n\
@@ -37,6 +39,13 @@ mock_connector!(ChannelRequest {
                                  {\"user_count\":1,\"occupied\":true,\"subscription_count\":1}"
 });
 
+mock_connector!(CacheChannelRequest {
+    "http://127.0.0.1" =>       "HTTP/1.1 200 OK\r\n\
+                                 Server: mock1\r\n\
+                                 \r\n\
+                                 {\"occupied\":true,\"cache\":{\"data\":\"{\\\"some\\\":\\\"data\\\"}\",\"ttl\":1800}}"
+});
+
 mock_connector!(ChannelUsersRequest {
     "http://127.0.0.1" =>       "HTTP/1.1 200 OK\r\n\
                                  Server: mock1\r\n\
@@ -44,6 +53,13 @@ mock_connector!(ChannelUsersRequest {
                                  {\"users\":[{\"id\":\"red\"},{\"id\":\"blue\"}]}"
 });
 
+mock_connector!(ChannelUsersWithInfoRequest {
+    "http://127.0.0.1" =>       "HTTP/1.1 200 OK\r\n\
+                                 Server: mock1\r\n\
+                                 \r\n\
+                                 {\"users\":[{\"id\":\"red\",\"name\":\"Red\"},{\"id\":\"blue\"}]}"
+});
+
 #[tokio::test]
 async fn test_error_response_handler() {
     let client = Client::builder().build(BadRequest::default());
@@ -101,10 +117,23 @@ async fn test_get_channel() {
     let occupied = channel.occupied.unwrap();
     let subscription_count = channel.subscription_count.unwrap();
     assert_eq!(user_count, 1);
-    assert_eq!(occupied, true);
+    assert!(occupied);
     assert_eq!(subscription_count, 1)
 }
 
+#[tokio::test]
+async fn test_get_cache_channel() {
+    let client = Client::builder().build(CacheChannelRequest::default());
+    let pusher = PusherBuilder::new_with_client(client, "1", "2", "3")
+        .host("127.0.0.1")
+        .finalize();
+    let query = ChannelQuery::new().info(ChannelInfo::Cache);
+    let res = pusher.channel_with_query("cache-foo", query).await;
+    let cache = res.unwrap().cache.unwrap();
+    assert_eq!(cache.data, "{\"some\":\"data\"}");
+    assert_eq!(cache.ttl, 1800)
+}
+
 #[tokio::test]
 async fn test_get_channel_users() {
     let client = Client::builder().build(ChannelUsersRequest::default());
@@ -118,3 +147,28 @@ async fn test_get_channel_users() {
     assert_eq!(user_one.id, "red");
     assert_eq!(user_two.id, "blue")
 }
+
+#[tokio::test]
+async fn test_stream_channel_users() {
+    let client = Client::builder().build(ChannelUsersWithInfoRequest::default());
+    let pusher = PusherBuilder::new_with_client(client, "1", "2", "3")
+        .host("127.0.0.1")
+        .finalize();
+    let users: Vec<_> = pusher.channel_users_stream("presence-yolo").collect().await;
+    assert_eq!(users.len(), 2);
+    let red = users[0].as_ref().unwrap();
+    assert_eq!(red.id, "red");
+    assert_eq!(red.info["name"], "Red");
+    assert!(users[1].as_ref().unwrap().info.is_empty())
+}
+
+#[tokio::test]
+async fn test_stream_channel_users_error() {
+    let client = Client::builder().build(BadRequest::default());
+    let pusher = PusherBuilder::new_with_client(client, "1", "2", "3")
+        .host("127.0.0.1")
+        .finalize();
+    let users: Vec<_> = pusher.channel_users_stream("presence-yolo").collect().await;
+    assert_eq!(users.len(), 1);
+    assert!(users[0].is_err())
+}
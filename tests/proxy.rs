@@ -0,0 +1,131 @@
+extern crate hyper;
+extern crate pusher;
+extern crate tokio;
+
+use hyper::client::connect::Connection;
+use hyper::client::HttpConnector;
+use hyper::service::{make_service_fn, service_fn, Service};
+use hyper::{Body, Request, Response, Server};
+use pusher::{Proxy, ProxyConnector, PusherBuilder};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// Serves the channels endpoint of the HTTP API.
+fn spawn_api() -> SocketAddr {
+    let make_svc = make_service_fn(|_conn| async {
+        Ok::<_, Infallible>(service_fn(|_req: Request<Body>| async {
+            Ok::<_, Infallible>(Response::new(Body::from(
+                "{\"channels\":{\"presence-a\":{\"user_count\":3}}}",
+            )))
+        }))
+    });
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    addr
+}
+
+// A stand-in for an HTTP proxy. It records the head of each request, and only
+// opens a tunnel for a CONNECT, or forwards a request with a full URL, if the
+// expected credentials are given.
+async fn spawn_proxy(authorization: &'static str) -> (SocketAddr, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut client, _) = listener.accept().await.unwrap();
+            let recorded = recorded.clone();
+            tokio::spawn(async move {
+                let mut head = Vec::new();
+                let mut buf = [0; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    let n = client.read(&mut buf).await.unwrap();
+                    head.extend_from_slice(&buf[..n]);
+                }
+                let head = String::from_utf8(head).unwrap();
+                recorded.lock().unwrap().push(head.clone());
+
+                if !head.contains(authorization) {
+                    let response = "HTTP/1.1 407 Proxy Authentication Required\r\n\r\n";
+                    client.write_all(response.as_bytes()).await.unwrap();
+                    return;
+                }
+                let target = head.split_whitespace().nth(1).unwrap().to_string();
+                let mut upstream = if head.starts_with("CONNECT ") {
+                    let upstream = TcpStream::connect(target).await.unwrap();
+                    let response = "HTTP/1.1 200 Connection established\r\n\r\n";
+                    client.write_all(response.as_bytes()).await.unwrap();
+                    upstream
+                } else {
+                    let uri: hyper::Uri = target.parse().unwrap();
+                    let authority = uri.authority().unwrap().as_str();
+                    let mut upstream = TcpStream::connect(authority).await.unwrap();
+                    upstream.write_all(head.as_bytes()).await.unwrap();
+                    upstream
+                };
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+            });
+        }
+    });
+    (addr, requests)
+}
+
+#[tokio::test]
+async fn test_proxy() {
+    let api_addr = spawn_api();
+    // base64("user:secret")
+    let (proxy_addr, requests) = spawn_proxy("proxy-authorization: Basic dXNlcjpzZWNyZXQ=").await;
+
+    let proxy = Proxy::new(&format!("http://user:secret@{}", proxy_addr)).unwrap();
+    let pusher = PusherBuilder::new("1", "key", "secret")
+        .host(&api_addr.to_string())
+        .insecure()
+        .proxy(proxy)
+        .finalize();
+    let channels = pusher.channels().await.unwrap();
+    assert_eq!(channels.channels["presence-a"].user_count, Some(3));
+
+    // A plain HTTP request is sent to the proxy with its full URL.
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    let request_line = format!("GET http://{}/apps/1/channels?", api_addr);
+    assert!(requests[0].starts_with(&request_line), "{}", requests[0])
+}
+
+#[tokio::test]
+async fn test_proxy_tunnels_https() {
+    let api_addr = spawn_api();
+    let (proxy_addr, requests) = spawn_proxy("Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=").await;
+
+    let proxy = Proxy::new(&format!("http://user:secret@{}", proxy_addr)).unwrap();
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    let mut connector = ProxyConnector::new(http).proxy(proxy);
+    let dst = format!("https://{}", api_addr).parse().unwrap();
+    let stream = connector.call(dst).await.unwrap();
+    assert!(!stream.connected().is_proxied());
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].starts_with(&format!("CONNECT {} HTTP/1.1\r\n", api_addr)))
+}
+
+#[tokio::test]
+async fn test_proxy_authentication_required() {
+    let api_addr = spawn_api();
+    let (proxy_addr, requests) = spawn_proxy("Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=").await;
+
+    let pusher = PusherBuilder::new("1", "key", "secret")
+        .host(&api_addr.to_string())
+        .insecure()
+        .proxy(Proxy::new(&format!("http://{}", proxy_addr)).unwrap())
+        .finalize();
+    let err = pusher.channels().await.unwrap_err();
+    assert!(err.contains("407"), "{}", err);
+    assert!(!requests.lock().unwrap()[0].contains("proxy-authorization"))
+}
@@ -0,0 +1,152 @@
+extern crate pusher;
+extern crate tokio;
+
+use pusher::{
+    BatchEvent, PublisherOptions, PusherBuilder, Transport, TransportFuture, TransportRequest,
+    TransportResponse,
+};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Answers with the scripted statuses in turn, then with 200.
+#[derive(Default)]
+struct BatchTransport {
+    statuses: Mutex<VecDeque<u16>>,
+    batches: Mutex<Vec<serde_json::Value>>,
+}
+
+impl BatchTransport {
+    fn failing(statuses: &[u16]) -> BatchTransport {
+        BatchTransport {
+            statuses: Mutex::new(statuses.iter().copied().collect()),
+            ..Default::default()
+        }
+    }
+
+    fn batch_sizes(&self) -> Vec<usize> {
+        let batches = self.batches.lock().unwrap();
+        batches
+            .iter()
+            .map(|batch| batch["batch"].as_array().unwrap().len())
+            .collect()
+    }
+}
+
+impl Transport for BatchTransport {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        let body = serde_json::from_str(request.body.as_deref().unwrap()).unwrap();
+        self.batches.lock().unwrap().push(body);
+        let status = self.statuses.lock().unwrap().pop_front().unwrap_or(200);
+        Box::pin(async move {
+            Ok(TransportResponse {
+                status,
+                headers: Vec::new(),
+                body: b"{\"batch\":[]}".to_vec(),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_publisher_batches_by_size_and_time() {
+    let pusher =
+        PusherBuilder::new_with_client(BatchTransport::default(), "1", "key", "secret").finalize();
+    let publisher = pusher.publisher(
+        PublisherOptions::new()
+            .max_batch_size(4)
+            .linger(Duration::from_millis(20)),
+    );
+
+    for i in 0..6 {
+        publisher
+            .try_publish(BatchEvent::new("a", "my_event", i))
+            .unwrap();
+    }
+    // A full batch goes out at once; the rest waits out the linger.
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    assert_eq!(pusher.http_client().batch_sizes(), vec![4]);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(pusher.http_client().batch_sizes(), vec![4, 2]);
+
+    let batches = pusher.http_client().batches.lock().unwrap();
+    assert_eq!(batches[1]["batch"][1]["data"], "5");
+    assert_eq!(batches[1]["batch"][1]["channel"], "a")
+}
+
+#[tokio::test]
+async fn test_publisher_retries_and_reports_failures() {
+    let transport = BatchTransport::failing(&[503, 503, 503, 400]);
+    let failures = Arc::new(Mutex::new(Vec::new()));
+    let reported = Arc::clone(&failures);
+    let pusher = PusherBuilder::new_with_client(transport, "1", "key", "secret").finalize();
+    let publisher = pusher.publisher(
+        PublisherOptions::new()
+            .retries(2, Duration::from_millis(1))
+            .on_error(move |events, err| {
+                reported
+                    .lock()
+                    .unwrap()
+                    .push((events.len(), err.to_string()))
+            }),
+    );
+
+    // Two attempts fail with a server error, so the batch is reported.
+    publisher
+        .publish(BatchEvent::new("a", "my_event", 1))
+        .await
+        .unwrap();
+    publisher.flush().await.unwrap();
+    // A server error, then a client error, which is not retried.
+    publisher
+        .publish(BatchEvent::new("a", "my_event", 2))
+        .await
+        .unwrap();
+    publisher.flush().await.unwrap();
+    // Retrying succeeds, after the scripted failures run out.
+    publisher
+        .publish(BatchEvent::new("a", "my_event", 3))
+        .await
+        .unwrap();
+    publisher.flush().await.unwrap();
+
+    assert_eq!(pusher.http_client().batch_sizes().len(), 5);
+    let failures = failures.lock().unwrap();
+    assert_eq!(failures.len(), 2);
+    assert!(failures[0].1.starts_with("Error: 503"), "{}", failures[0].1);
+    assert!(failures[1].1.starts_with("Error: 400"), "{}", failures[1].1);
+}
+
+#[tokio::test]
+async fn test_publisher_queue_limits() {
+    let pusher =
+        PusherBuilder::new_with_client(BatchTransport::default(), "1", "key", "secret").finalize();
+    let publisher = pusher.publisher(PublisherOptions::new().capacity(1));
+
+    assert_eq!(
+        publisher.try_publish(BatchEvent::new("w000^$$", "my_event", 1)),
+        Err("Channels must be formatted as such: ^[-a-zA-Z0-9_=@,.;]+$".to_string())
+    );
+    // The background task has not had a chance to run yet.
+    publisher
+        .try_publish(BatchEvent::new("a", "my_event", 1))
+        .unwrap();
+    assert_eq!(
+        publisher.try_publish(BatchEvent::new("a", "my_event", 2)),
+        Err("Publisher queue is full".to_string())
+    );
+
+    // Shutting down sends what is queued, and stops the task for every clone.
+    let other = publisher.clone();
+    publisher.shutdown().await;
+    assert_eq!(pusher.http_client().batch_sizes(), vec![1]);
+    assert_eq!(
+        other.try_publish(BatchEvent::new("a", "my_event", 3)),
+        Err("Publisher is shut down".to_string())
+    );
+    assert_eq!(
+        other.flush().await,
+        Err("Publisher is shut down".to_string())
+    );
+    other.shutdown().await
+}
@@ -0,0 +1,349 @@
+#![cfg(feature = "testing")]
+
+extern crate pusher;
+extern crate tokio;
+
+use pusher::testing::{FakePusher, MockServer};
+use pusher::{
+    BatchEvent, Channel, ChannelInfo, ChannelQuery, ChannelUser, ChannelsQuery, PublisherOptions,
+    PusherApi, PusherBuilder, SocketId,
+};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+#[tokio::test]
+async fn test_mock_server_records_events() {
+    let server = MockServer::start("1", "key", "secret");
+    let pusher = PusherBuilder::from_url(&server.url()).finalize();
+
+    pusher
+        .trigger_multi_exclusive(&["a", "b"], "my_event", "hello", "1234.1234")
+        .await
+        .unwrap();
+
+    let events = server.events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].name, "my_event");
+    assert_eq!(events[0].channels, vec!["a", "b"]);
+    assert_eq!(events[0].data, "\"hello\"");
+    assert_eq!(events[0].socket_id.as_deref(), Some("1234.1234"));
+
+    let requests = server.requests();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/apps/1/events");
+    assert!(requests[0].query.contains_key("body_md5"))
+}
+
+#[tokio::test]
+async fn test_mock_server_rejects_bad_signatures() {
+    let server = MockServer::start("1", "key", "secret");
+    let pusher = PusherBuilder::new("1", "key", "wrong")
+        .host(&server.addr().to_string())
+        .insecure()
+        .finalize();
+
+    let err = pusher.trigger("a", "my_event", "hello").await.unwrap_err();
+    assert!(err.contains("401"), "{}", err);
+    assert!(err.contains("Invalid signature"), "{}", err);
+    assert!(server.events().is_empty());
+    assert_eq!(server.requests().len(), 1)
+}
+
+#[tokio::test]
+async fn test_mock_server_with_clock() {
+    let clock = || UNIX_EPOCH + Duration::from_secs(1353088179);
+    let server = MockServer::start("1", "key", "secret");
+    let pusher = PusherBuilder::from_url(&server.url())
+        .clock(clock)
+        .finalize();
+    let err = pusher.trigger("a", "my_event", "hello").await.unwrap_err();
+    assert!(err.contains("Timestamp expired"), "{}", err);
+
+    let server = MockServer::start_with_clock("1", "key", "secret", clock);
+    let pusher = PusherBuilder::from_url(&server.url())
+        .clock(clock)
+        .finalize();
+    pusher.trigger("a", "my_event", "hello").await.unwrap();
+    assert_eq!(server.events().len(), 1)
+}
+
+#[tokio::test]
+async fn test_mock_server_base_path() {
+    let server = MockServer::start("1", "key", "secret");
+    let pusher = PusherBuilder::new("1", "key", "secret")
+        .host(&server.addr().ip().to_string())
+        .port(server.addr().port())
+        .base_path("/pusher")
+        .insecure()
+        .finalize();
+
+    pusher.trigger("a", "my_event", "hello").await.unwrap();
+    assert_eq!(server.requests()[0].path, "/pusher/apps/1/events")
+}
+
+#[tokio::test]
+async fn test_mock_server_channels() {
+    let server = MockServer::start("1", "key", "secret");
+    server.set_channel(
+        "cache-a",
+        Channel {
+            occupied: Some(true),
+            subscription_count: Some(2),
+            ..Channel::default()
+        },
+    );
+    let users = vec![ChannelUser {
+        id: "red".to_string(),
+        info: HashMap::new(),
+    }];
+    server.set_users("presence-b", users);
+    let pusher = PusherBuilder::from_url(&server.url()).finalize();
+
+    let channels = pusher
+        .channels_with_query(
+            ChannelsQuery::new()
+                .filter_by_prefix("presence-")
+                .info(ChannelInfo::UserCount),
+        )
+        .await
+        .unwrap();
+    assert_eq!(channels.channels.len(), 1);
+    assert_eq!(channels.channels["presence-b"].user_count, Some(1));
+
+    let channel = pusher
+        .channel_with_query(
+            "cache-a",
+            ChannelQuery::new().info(ChannelInfo::SubscriptionCount),
+        )
+        .await
+        .unwrap();
+    assert_eq!(channel.occupied, Some(true));
+    assert_eq!(channel.subscription_count, Some(2));
+
+    let channels = pusher
+        .channels_with_query(ChannelsQuery::new().info(ChannelInfo::SubscriptionCount))
+        .await
+        .unwrap();
+    assert_eq!(channels.channels["cache-a"].subscription_count, Some(2));
+    assert_eq!(channels.channels["cache-a"].user_count, None);
+    assert_eq!(channels.channels["presence-b"].subscription_count, None);
+
+    let channel = pusher.channel("cache-unknown").await.unwrap();
+    assert_eq!(channel.occupied, Some(false));
+
+    let users = pusher.channel_users("presence-b").await.unwrap();
+    assert_eq!(users.users[0].id, "red")
+}
+
+#[tokio::test]
+async fn test_mock_server_batch_events() {
+    let server = MockServer::start("1", "key", "secret");
+    let pusher = PusherBuilder::from_url(&server.url()).finalize();
+
+    let socket_id = SocketId::new("1234.1234").unwrap();
+    let events = vec![
+        BatchEvent::new("a", "my_event", "hello"),
+        BatchEvent::new("b", "other_event", 2).socket_id(&socket_id),
+    ];
+    let triggered = pusher.trigger_batch(events).await.unwrap();
+    assert_eq!(triggered.batch.len(), 2);
+
+    let events = server.events();
+    assert_eq!(events[0].channels, vec!["a"]);
+    assert_eq!(events[0].data, "\"hello\"");
+    assert_eq!(events[1].name, "other_event");
+    assert_eq!(events[1].socket_id.as_deref(), Some("1234.1234"));
+    assert_eq!(server.requests()[0].path, "/apps/1/batch_events")
+}
+
+#[tokio::test]
+async fn test_mock_server_publisher() {
+    let server = MockServer::start("1", "key", "secret");
+    let pusher = PusherBuilder::from_url(&server.url()).finalize();
+    let publisher = pusher.publisher(PublisherOptions::new().linger(Duration::from_secs(60)));
+
+    for i in 0..25 {
+        publisher
+            .publish(BatchEvent::new("a", "my_event", i))
+            .await
+            .unwrap();
+    }
+    publisher.flush().await.unwrap();
+    assert_eq!(server.requests().len(), 3);
+    let data: Vec<String> = server
+        .events()
+        .into_iter()
+        .map(|event| event.data)
+        .collect();
+    let expected: Vec<String> = (0..25).map(|i| i.to_string()).collect();
+    assert_eq!(data, expected);
+
+    publisher
+        .publish(BatchEvent::new("a", "my_event", "last"))
+        .await
+        .unwrap();
+    publisher.shutdown().await;
+    assert_eq!(server.events().len(), 26);
+    assert_eq!(
+        publisher
+            .publish(BatchEvent::new("a", "my_event", "late"))
+            .await,
+        Err("Publisher is shut down".to_string())
+    )
+}
+
+#[tokio::test]
+async fn test_mock_server_failures_and_delays() {
+    let server = MockServer::start("1", "key", "secret");
+    let pusher = PusherBuilder::from_url(&server.url()).finalize();
+
+    server.fail_next(500, "Internal error");
+    let err = pusher.trigger("a", "my_event", "hello").await.unwrap_err();
+    assert!(err.contains("500"), "{}", err);
+    assert!(server.events().is_empty());
+
+    server.set_delay(Some(Duration::from_millis(100)));
+    let start = Instant::now();
+    pusher.trigger("a", "my_event", "hello").await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert_eq!(server.events().len(), 1)
+}
+
+#[tokio::test]
+async fn test_mock_server_with_tuned_client() {
+    let server = MockServer::start("1", "key", "secret");
+    let pusher = PusherBuilder::from_url(&server.url())
+        .pool_idle_timeout(Duration::from_secs(5))
+        .pool_max_idle_per_host(1)
+        .http2_prior_knowledge()
+        .finalize();
+
+    let triggers = (0..5).map(|i| pusher.trigger("a", "my_event", i));
+    for result in futures_util::future::join_all(triggers).await {
+        result.unwrap();
+    }
+    assert_eq!(server.events().len(), 5)
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_mock_server_with_blocking_client() {
+    let server = MockServer::start("1", "key", "secret");
+    let pusher = pusher::blocking::PusherBuilder::from_url(&server.url()).finalize();
+
+    pusher.trigger("a", "my_event", "hello").unwrap();
+    assert_eq!(server.events()[0].name, "my_event")
+}
+
+// Application code, written against the trait so that it can be unit tested.
+async fn greet_members<P: PusherApi>(pusher: &P, channel_name: &str) -> Result<usize, String> {
+    let users = pusher.channel_users(channel_name).await?.users;
+    for user in &users {
+        let channel = format!("private-user-{}", user.id);
+        pusher.trigger(&channel, "greeting", "hello").await?;
+    }
+    Ok(users.len())
+}
+
+fn users(ids: &[&str]) -> Vec<ChannelUser> {
+    ids.iter()
+        .map(|id| ChannelUser {
+            id: id.to_string(),
+            info: HashMap::new(),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_fake_pusher() {
+    let pusher = FakePusher::new("1", "key", "secret");
+    pusher.set_users("presence-room", users(&["red", "blue"]));
+
+    assert_eq!(greet_members(&pusher, "presence-room").await, Ok(2));
+    assert_eq!(pusher.events().len(), 2);
+    let events = pusher.events_on("private-user-blue");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].name, "greeting");
+    assert!(pusher.events_on("private-user-green").is_empty())
+}
+
+#[tokio::test]
+async fn test_fake_pusher_scripted_responses() {
+    let pusher = FakePusher::new("1", "key", "secret");
+    pusher.set_channel(
+        "presence-room",
+        Channel {
+            occupied: Some(true),
+            user_count: Some(5),
+            ..Channel::default()
+        },
+    );
+    let channel = pusher
+        .channel_with_query(
+            "presence-room",
+            ChannelQuery::new().info(ChannelInfo::UserCount),
+        )
+        .await
+        .unwrap();
+    assert_eq!(channel.user_count, Some(5));
+
+    pusher.fail_next(503, "Service unavailable");
+    let err = pusher.trigger("a", "my_event", "hello").await.unwrap_err();
+    assert!(err.contains("503"), "{}", err);
+    assert!(pusher.events().is_empty());
+
+    // Validation happens as it would for a real Pusher.
+    let err = pusher
+        .trigger("a:b", "my_event", "hello")
+        .await
+        .unwrap_err();
+    assert!(err.starts_with("Channels must be formatted"), "{}", err);
+    assert_eq!(pusher.requests().len(), 2)
+}
+
+#[tokio::test]
+async fn test_fake_pusher_covers_api() {
+    let pusher = FakePusher::new("1", "key", "secret");
+    let channels: Vec<String> = (0..150).map(|i| format!("user-{}", i)).collect();
+    let channels: Vec<&str> = channels.iter().map(String::as_str).collect();
+    PusherApi::trigger_many_channels(&pusher, &channels, "my_event", "hello", 2)
+        .await
+        .unwrap();
+    assert_eq!(pusher.events().len(), 2);
+    assert_eq!(pusher.events_on("user-149").len(), 1);
+
+    pusher.set_users("presence-room", users(&["red"]));
+    let params = vec![("info".to_string(), "user_count".to_string())];
+    let channel = pusher
+        .channel_with_options("presence-room", params.clone())
+        .await
+        .unwrap();
+    assert_eq!(channel.user_count, Some(1));
+    let list = pusher.channels_with_options(params).await.unwrap();
+    assert_eq!(list.channels["presence-room"].user_count, Some(1));
+
+    let webhook = pusher.webhook("key", "00", "{}");
+    assert_eq!(webhook.unwrap_err(), "Invalid webhook")
+}
+
+#[tokio::test]
+async fn test_fake_pusher_authentication() {
+    let pusher = FakePusher::new("1", "278d425bdf160c739803", "7ad3773142a6692b25b8");
+    let auth = pusher
+        .authenticate_private_channel("private-foobar", "1234.1234")
+        .unwrap();
+    assert_eq!(
+        auth,
+        "{\"auth\":\"278d425bdf160c739803:58df8b0c36d6982b82c3ecf6b4662e34fe8c25bba48f5369f135bf843651c3a4\"}"
+    )
+}
+
+#[tokio::test]
+async fn test_pusher_implements_api() {
+    let server = MockServer::start("1", "key", "secret");
+    server.set_users("presence-room", users(&["red"]));
+    let pusher = PusherBuilder::from_url(&server.url()).finalize();
+
+    assert_eq!(greet_members(&pusher, "presence-room").await, Ok(1));
+    assert_eq!(server.events()[0].channels, vec!["private-user-red"])
+}
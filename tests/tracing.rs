@@ -0,0 +1,110 @@
+#![cfg(feature = "tracing")]
+
+extern crate pusher;
+extern crate tokio;
+extern crate tracing_subscriber;
+
+use pusher::{
+    PusherApi, PusherBuilder, Transport, TransportFuture, TransportRequest, TransportResponse,
+};
+use std::io;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::util::SubscriberInitExt;
+
+struct CannedTransport {
+    status: u16,
+    body: &'static str,
+}
+
+impl Transport for CannedTransport {
+    fn send(&self, _request: TransportRequest) -> TransportFuture<'_> {
+        let response = TransportResponse {
+            status: self.status,
+            headers: Vec::new(),
+            body: self.body.as_bytes().to_vec(),
+        };
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+fn capture() -> (Output, tracing::subscriber::DefaultGuard) {
+    let output = Output::default();
+    let writer = output.clone();
+    let guard = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_writer(move || writer.clone())
+        .finish()
+        .set_default();
+    (output, guard)
+}
+
+#[tokio::test]
+async fn test_trigger_spans() {
+    let (output, _guard) = capture();
+    let transport = CannedTransport {
+        status: 200,
+        body: "{}",
+    };
+    let pusher = PusherBuilder::new_with_client(transport, "1", "key", "supersecret").finalize();
+    pusher
+        .trigger_multi(&["a", "b"], "my_event", "hello")
+        .await
+        .unwrap();
+
+    let output = output.contents();
+    assert!(
+        output.contains("pusher.trigger{event=\"my_event\" channels=2}"),
+        "{}",
+        output
+    );
+    assert!(
+        output.contains("method=POST path=\"/apps/1/events\""),
+        "{}",
+        output
+    );
+    assert!(
+        output.contains("attempt=1 status=200 latency_ms="),
+        "{}",
+        output
+    );
+    assert!(output.contains("payload_size=76"), "{}", output);
+    assert!(!output.contains("supersecret"), "{}", output);
+    assert!(!output.contains("auth_signature"), "{}", output)
+}
+
+#[tokio::test]
+async fn test_failed_request_events() {
+    let (output, _guard) = capture();
+    let transport = CannedTransport {
+        status: 500,
+        body: "Internal error",
+    };
+    let pusher = PusherBuilder::new_with_client(transport, "1", "key", "supersecret").finalize();
+    PusherApi::channel(&pusher, "a").await.unwrap_err();
+
+    let output = output.contents();
+    assert!(output.contains("WARN"), "{}", output);
+    assert!(output.contains("path=\"/apps/1/channels/a\""), "{}", output);
+    assert!(output.contains("request failed status=500"), "{}", output);
+    assert!(!output.contains("auth_signature"), "{}", output)
+}
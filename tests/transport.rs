@@ -0,0 +1,546 @@
+extern crate hyper;
+extern crate pusher;
+extern crate tokio;
+
+use pusher::{
+    Interceptor, Next, PusherBuilder, RateLimit, RequestInfo, RequestObserver, RequestVerifier,
+    ResponseInfo, Transport, TransportFuture, TransportRequest, TransportResponse,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+struct RecordingTransport {
+    requests: Mutex<Vec<TransportRequest>>,
+}
+
+impl Transport for RecordingTransport {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        self.requests.lock().unwrap().push(request);
+        Box::pin(async {
+            Ok(TransportResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: b"{\"event_ids\":{\"test_channel\":\"eudhq1809scss2\"}}".to_vec(),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_custom_transport() {
+    let transport = RecordingTransport {
+        requests: Mutex::new(Vec::new()),
+    };
+    let pusher = PusherBuilder::new_with_client(transport, "1", "key", "secret")
+        .host("127.0.0.1")
+        .finalize();
+    let events = pusher.trigger("test_channel", "my_event", "hello").await;
+    assert_eq!(
+        events.unwrap().event_ids.unwrap()["test_channel"],
+        "eudhq1809scss2"
+    );
+
+    let requests = pusher.http_client().requests.lock().unwrap();
+    let request = &requests[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.url.path(), "/apps/1/events");
+    assert!(request.url.query().unwrap().contains("auth_signature="));
+    assert!(request
+        .body
+        .as_ref()
+        .unwrap()
+        .contains("\"name\":\"my_event\""));
+    assert!(request
+        .headers
+        .contains(&("content-type".to_string(), "application/json".to_string())))
+}
+
+#[tokio::test]
+async fn test_signed_url_with_fixed_clock() {
+    let transport = RecordingTransport {
+        requests: Mutex::new(Vec::new()),
+    };
+    let pusher = PusherBuilder::new_with_client(transport, "1", "key", "secret")
+        .host("127.0.0.1")
+        .insecure()
+        .clock(|| UNIX_EPOCH + Duration::from_secs(1353088179))
+        .finalize();
+    pusher
+        .trigger("test_channel", "my_event", "hello")
+        .await
+        .unwrap();
+
+    let requests = pusher.http_client().requests.lock().unwrap();
+    assert_eq!(
+        requests[0].url.as_str(),
+        "http://127.0.0.1/apps/1/events?auth_key=key&auth_timestamp=1353088179&auth_version=1.0&body_md5=162a5af5c14a2639660c5a3eb66c281a&auth_signature=12240a69140548ebda74b2cac4b632fe420594c778e153e0db4ff84186557991"
+    )
+}
+
+struct RecordingObserver {
+    requests: Arc<Mutex<Vec<RequestInfo>>>,
+    responses: Arc<Mutex<Vec<ResponseInfo>>>,
+}
+
+impl RequestObserver for RecordingObserver {
+    fn on_request(&self, request: &RequestInfo) {
+        self.requests.lock().unwrap().push(request.clone());
+    }
+
+    fn on_response(&self, request: &RequestInfo, response: &ResponseInfo) {
+        assert_eq!(self.requests.lock().unwrap().last(), Some(request));
+        self.responses.lock().unwrap().push(*response);
+    }
+}
+
+#[tokio::test]
+async fn test_request_observer() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let transport = RecordingTransport {
+        requests: Mutex::new(Vec::new()),
+    };
+    let pusher = PusherBuilder::new_with_client(transport, "1", "key", "secret")
+        .observer(RecordingObserver {
+            requests: requests.clone(),
+            responses: responses.clone(),
+        })
+        .finalize();
+
+    pusher
+        .trigger_multi(&["a", "private-b"], "my_event", "hello")
+        .await
+        .unwrap();
+    pusher.channel_users("presence-c").await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/apps/1/events");
+    assert_eq!(requests[0].event.as_deref(), Some("my_event"));
+    assert_eq!(requests[0].channels, vec!["a", "private-b"]);
+    assert!(requests[0].payload_size > 0);
+    assert_eq!(requests[1].path, "/apps/1/channels/presence-c/users");
+    assert_eq!(requests[1].event, None);
+    assert_eq!(requests[1].channels, vec!["presence-c"]);
+    assert_eq!(requests[1].payload_size, 0);
+
+    let responses = responses.lock().unwrap();
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[0].status, Some(200));
+    assert_eq!(responses[0].error, None)
+}
+
+struct AddHeader(&'static str, &'static str);
+
+impl Interceptor for AddHeader {
+    fn intercept<'a>(
+        &'a self,
+        mut request: TransportRequest,
+        next: Next<'a>,
+    ) -> TransportFuture<'a> {
+        request
+            .headers
+            .push((self.0.to_string(), self.1.to_string()));
+        next.run(request)
+    }
+}
+
+struct Reroute;
+
+impl Interceptor for Reroute {
+    fn intercept<'a>(
+        &'a self,
+        mut request: TransportRequest,
+        next: Next<'a>,
+    ) -> TransportFuture<'a> {
+        request.url.set_host(Some("api-eu.pusher.com")).unwrap();
+        next.run(request)
+    }
+}
+
+#[tokio::test]
+async fn test_interceptors() {
+    let transport = RecordingTransport {
+        requests: Mutex::new(Vec::new()),
+    };
+    let pusher = PusherBuilder::new_with_client(transport, "1", "key", "secret")
+        .interceptor(AddHeader("x-tenant-id", "acme"))
+        .interceptor(Reroute)
+        .interceptor(AddHeader("x-tenant-id", "other"))
+        .finalize();
+    pusher
+        .trigger("test_channel", "my_event", "hello")
+        .await
+        .unwrap();
+
+    let requests = pusher.http_client().requests.lock().unwrap();
+    let request = &requests[0];
+    assert_eq!(request.url.host_str(), Some("api-eu.pusher.com"));
+    assert_eq!(request.url.path(), "/apps/1/events");
+    let tenants: Vec<&str> = request
+        .headers
+        .iter()
+        .filter(|(name, _)| name == "x-tenant-id")
+        .map(|(_, value)| value.as_str())
+        .collect();
+    assert_eq!(tenants, vec!["acme", "other"])
+}
+
+#[cfg(feature = "tower")]
+#[tokio::test]
+async fn test_tower_layers() {
+    use pusher::{ServiceTransport, TransportService};
+    use tower::ServiceBuilder;
+
+    struct SlowTransport;
+
+    impl Transport for SlowTransport {
+        fn send(&self, _request: TransportRequest) -> TransportFuture<'_> {
+            Box::pin(async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Err("Error: too slow".to_string())
+            })
+        }
+    }
+
+    let recording = Arc::new(RecordingTransport {
+        requests: Mutex::new(Vec::new()),
+    });
+    let service = ServiceBuilder::new()
+        .map_request(|mut request: TransportRequest| {
+            request
+                .headers
+                .push(("x-tenant-id".to_string(), "acme".to_string()));
+            request
+        })
+        .service(TransportService::new(recording.clone()));
+    let pusher =
+        PusherBuilder::new_with_client(ServiceTransport::new(service), "1", "key", "secret")
+            .finalize();
+    pusher
+        .trigger("test_channel", "my_event", "hello")
+        .await
+        .unwrap();
+    let headers = recording.requests.lock().unwrap()[0].headers.clone();
+    assert!(headers.contains(&("x-tenant-id".to_string(), "acme".to_string())));
+
+    let service = ServiceBuilder::new()
+        .timeout(Duration::from_millis(10))
+        .service(TransportService::new(SlowTransport));
+    let pusher =
+        PusherBuilder::new_with_client(ServiceTransport::new(service), "1", "key", "secret")
+            .finalize();
+    let err = pusher.channels().await.unwrap_err();
+    assert_eq!(err, "Error: request timed out")
+}
+
+// Answers with the given statuses in turn, asking to retry immediately.
+struct ThrottledTransport {
+    statuses: Mutex<Vec<u16>>,
+    sent: Mutex<Vec<TransportRequest>>,
+}
+
+impl Transport for ThrottledTransport {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        self.sent.lock().unwrap().push(request);
+        let status = self.statuses.lock().unwrap().remove(0);
+        Box::pin(async move {
+            Ok(TransportResponse {
+                status,
+                headers: vec![("retry-after".to_string(), "0".to_string())],
+                body: b"{}".to_vec(),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limit() {
+    let transport = ThrottledTransport {
+        statuses: Mutex::new(vec![429, 429, 200, 429, 429, 429, 200]),
+        sent: Mutex::new(Vec::new()),
+    };
+    let pusher = PusherBuilder::new_with_client(transport, "1", "key", "secret")
+        .rate_limit(RateLimit::new(1000, 10))
+        .finalize();
+
+    // A 429 is retried after the Retry-After delay, up to three attempts.
+    pusher.trigger("a", "my_event", "hello").await.unwrap();
+    assert_eq!(pusher.http_client().sent.lock().unwrap().len(), 3);
+    let err = pusher.trigger("a", "my_event", "hello").await.unwrap_err();
+    assert!(err.contains("429"), "{}", err);
+    assert_eq!(pusher.http_client().sent.lock().unwrap().len(), 6);
+
+    // Queries are neither limited nor retried.
+    pusher.channels().await.unwrap();
+    assert_eq!(pusher.http_client().sent.lock().unwrap().len(), 7);
+}
+
+#[tokio::test]
+async fn test_retry_after_without_rate_limit() {
+    let transport = ThrottledTransport {
+        statuses: Mutex::new(vec![429, 200]),
+        sent: Mutex::new(Vec::new()),
+    };
+    let pusher = PusherBuilder::new_with_client(transport, "1", "key", "secret").finalize();
+
+    pusher.trigger("a", "my_event", "hello").await.unwrap();
+    assert_eq!(pusher.http_client().sent.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_retry_is_signed_again() {
+    let transport = ThrottledTransport {
+        statuses: Mutex::new(vec![429, 200]),
+        sent: Mutex::new(Vec::new()),
+    };
+    // Each reading of the clock is a minute later than the last.
+    let ticks = AtomicU64::new(0);
+    let pusher = PusherBuilder::new_with_client(transport, "1", "key", "secret")
+        .clock(move || {
+            let tick = ticks.fetch_add(1, Ordering::SeqCst);
+            UNIX_EPOCH + Duration::from_secs(1353088179 + tick * 60)
+        })
+        .finalize();
+
+    pusher.trigger("a", "my_event", "hello").await.unwrap();
+    let sent = pusher.http_client().sent.lock().unwrap();
+    let timestamps: Vec<String> = sent
+        .iter()
+        .map(|request| {
+            let mut pairs = request.url.query_pairs();
+            let (_, timestamp) = pairs.find(|(k, _)| k == "auth_timestamp").unwrap();
+            timestamp.into_owned()
+        })
+        .collect();
+    assert_eq!(timestamps, vec!["1353088179", "1353088239"]);
+    // The retry keeps the body, and is signed for it.
+    assert_eq!(sent[0].body, sent[1].body);
+    let verifier = RequestVerifier::new("key", "secret")
+        .clock(|| UNIX_EPOCH + Duration::from_secs(1353088239));
+    let retry = &sent[1];
+    let verified = verifier.verify(
+        &retry.method,
+        retry.url.path(),
+        retry.url.query().unwrap(),
+        retry.body.as_deref().unwrap(),
+    );
+    assert_eq!(verified, Ok(()))
+}
+
+#[tokio::test]
+async fn test_rate_limit_rejects() {
+    let transport = ThrottledTransport {
+        statuses: Mutex::new(vec![200, 200]),
+        sent: Mutex::new(Vec::new()),
+    };
+    let pusher = PusherBuilder::new_with_client(transport, "1", "key", "secret")
+        .rate_limit(RateLimit::new(1, 3).reject())
+        .finalize();
+
+    // Each channel counts as a message.
+    pusher
+        .trigger_multi(&["a", "b", "c"], "my_event", "hello")
+        .await
+        .unwrap();
+    let err = pusher.trigger("a", "my_event", "hello").await.unwrap_err();
+    assert_eq!(err, "Rate limit exceeded");
+    assert_eq!(pusher.http_client().sent.lock().unwrap().len(), 1);
+}
+
+// Tracks how many requests are being sent at once.
+#[derive(Default)]
+struct ConcurrencyTransport {
+    in_flight: Mutex<usize>,
+    max_in_flight: Mutex<usize>,
+}
+
+impl Transport for ConcurrencyTransport {
+    fn send(&self, _request: TransportRequest) -> TransportFuture<'_> {
+        Box::pin(async move {
+            {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                *in_flight += 1;
+                let mut max_in_flight = self.max_in_flight.lock().unwrap();
+                *max_in_flight = (*max_in_flight).max(*in_flight);
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            *self.in_flight.lock().unwrap() -= 1;
+            Ok(TransportResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: b"{}".to_vec(),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_max_in_flight() {
+    let pusher =
+        PusherBuilder::new_with_client(ConcurrencyTransport::default(), "1", "key", "secret")
+            .max_in_flight(3)
+            .finalize();
+    let triggers = (0..10).map(|_| pusher.trigger("a", "my_event", "hello"));
+    for result in futures_util::future::join_all(triggers).await {
+        result.unwrap();
+    }
+    assert_eq!(*pusher.http_client().max_in_flight.lock().unwrap(), 3);
+}
+
+// Serves the HTTP API on a background thread, so that blocking transports can
+// be tested too. Requests must be signed and carry a JSON content type.
+// Answers each trigger with an event id for each of its channels.
+#[derive(Default)]
+struct EventIdTransport {
+    channel_counts: Mutex<Vec<usize>>,
+}
+
+impl Transport for EventIdTransport {
+    fn send(&self, request: TransportRequest) -> TransportFuture<'_> {
+        let body: serde_json::Value =
+            serde_json::from_str(request.body.as_deref().unwrap()).unwrap();
+        let channels = body["channels"].as_array().unwrap();
+        self.channel_counts.lock().unwrap().push(channels.len());
+        let event_ids: serde_json::Map<String, serde_json::Value> = channels
+            .iter()
+            .map(|channel| {
+                let channel = channel.as_str().unwrap();
+                (channel.to_string(), format!("id-{}", channel).into())
+            })
+            .collect();
+        let body = serde_json::json!({ "event_ids": event_ids }).to_string();
+        Box::pin(async move {
+            Ok(TransportResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: body.into_bytes(),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_trigger_many_channels() {
+    let pusher = PusherBuilder::new_with_client(EventIdTransport::default(), "1", "key", "secret")
+        .finalize();
+    let channels: Vec<String> = (0..250).map(|i| format!("channel-{}", i)).collect();
+    let mut channels: Vec<&str> = channels.iter().map(String::as_str).collect();
+
+    let events = pusher
+        .trigger_many_channels(&channels, "my_event", "hello", 2)
+        .await
+        .unwrap();
+    let event_ids = events.event_ids.unwrap();
+    assert_eq!(event_ids.len(), 250);
+    assert_eq!(event_ids["channel-249"], "id-channel-249");
+    assert_eq!(
+        *pusher.http_client().channel_counts.lock().unwrap(),
+        vec![100, 100, 50]
+    );
+
+    // Nothing is sent if any channel is invalid.
+    channels.push("w000^$$");
+    let res = pusher
+        .trigger_many_channels(&channels, "my_event", "hello", 1)
+        .await;
+    assert!(res.is_err());
+    assert_eq!(pusher.http_client().channel_counts.lock().unwrap().len(), 3);
+
+    let pusher = PusherBuilder::new_with_client(EventIdTransport::default(), "1", "key", "secret")
+        .max_channels_per_trigger(10)
+        .finalize();
+    let events = pusher
+        .trigger_many_channels(&channels[..25], "my_event", "hello", 1)
+        .await
+        .unwrap();
+    assert_eq!(events.event_ids.unwrap().len(), 25);
+    let channel_counts = pusher.http_client().channel_counts.lock().unwrap();
+    assert_eq!(*channel_counts, vec![10, 10, 5])
+}
+
+#[cfg(any(feature = "hyper1", feature = "reqwest", feature = "ureq"))]
+fn spawn_server() -> std::net::SocketAddr {
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server, StatusCode};
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::mpsc;
+    use std::thread;
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async move {
+            let make_svc = make_service_fn(|_conn| async {
+                Ok::<_, Infallible>(service_fn(|req: Request<Body>| async move {
+                    let signed = req.uri().query().unwrap_or("").contains("auth_signature=");
+                    let json = req
+                        .headers()
+                        .get("content-type")
+                        .is_some_and(|v| v == "application/json");
+                    let response = if signed && json {
+                        Response::new(Body::from(
+                            "{\"channels\":{\"presence-a\":{\"user_count\":3}}}",
+                        ))
+                    } else {
+                        let mut response = Response::new(Body::from("Unsigned request"));
+                        *response.status_mut() = StatusCode::BAD_REQUEST;
+                        response
+                    };
+                    Ok::<_, Infallible>(response)
+                }))
+            });
+            let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+            tx.send(server.local_addr()).unwrap();
+            server.await.unwrap();
+        });
+    });
+    rx.recv().unwrap()
+}
+
+#[cfg(feature = "hyper1")]
+#[tokio::test]
+async fn test_hyper1_transport() {
+    use hyper_util::client::legacy::Client;
+    use hyper_util::rt::TokioExecutor;
+
+    let addr = spawn_server();
+    let client = Client::builder(TokioExecutor::new()).build_http();
+    let pusher = PusherBuilder::new_with_client(client, "1", "key", "secret")
+        .host(&addr.to_string())
+        .insecure()
+        .finalize();
+    let channels = pusher.channels().await.unwrap();
+    assert_eq!(channels.channels["presence-a"].user_count, Some(3))
+}
+
+#[cfg(feature = "reqwest")]
+#[tokio::test]
+async fn test_reqwest_transport() {
+    let addr = spawn_server();
+    let pusher = PusherBuilder::new_with_client(reqwest::Client::new(), "1", "key", "secret")
+        .host(&addr.to_string())
+        .insecure()
+        .finalize();
+    let channels = pusher.channels().await.unwrap();
+    assert_eq!(channels.channels["presence-a"].user_count, Some(3))
+}
+
+#[cfg(feature = "ureq")]
+#[test]
+fn test_ureq_transport() {
+    use pusher::BlockingTransport;
+
+    let addr = spawn_server();
+    let agent = ureq::Agent::new();
+    let url = format!("http://{}/apps/1/channels", addr).parse().unwrap();
+    let response = BlockingTransport::send(&agent, TransportRequest::new("GET", url, None));
+    let response = response.unwrap();
+    assert_eq!(response.status, 400);
+    assert_eq!(response.body, b"Unsigned request".to_vec())
+}